@@ -9,7 +9,7 @@ fn main() {
         _marker: std::marker::PhantomData,
     };
     #[cfg(any(feature = "latest-halo2", feature = "v030-halo2"))]
-    println!("{:?}", halo2_utils::estimate_k(&circuit));
+    println!("{:?}", halo2_utils::estimate_k(&circuit).unwrap());
 
     #[cfg(not(any(feature = "latest-halo2", feature = "v030-halo2")))]
     panic!("This example is only supported under the 'latest-halo2' or 'v030-halo2' feature")
@@ -7,5 +7,8 @@ fn main() {
         b: Fr::from(3),
         _marker: std::marker::PhantomData,
     };
-    println!("{:#?}", halo2_utils::infer_instance(&circuit, None));
+    println!(
+        "{:#?}",
+        halo2_utils::infer_instance(&circuit, None).unwrap()
+    );
 }
@@ -0,0 +1,18 @@
+use halo2_utils::{example_circuit::FactorisationCircuit, halo2_proofs::halo2curves::bn256::Fr};
+
+fn main() {
+    halo2_utils::cost::print::<Fr, FactorisationCircuit<Fr>>(4);
+}
+
+// output
+//
+// k: 4
+// max gate degree: 3
+// degree: 3
+// quotient degree: 2
+// extended domain: 2^5 = 32
+// commitments: 9
+// evaluations: 12
+// proof size: 672 bytes
+// verifier msm terms: 13
+// verifier pairings: 2
@@ -0,0 +1,11 @@
+use halo2_utils::{example_circuit::FactorisationCircuit, halo2_proofs::halo2curves::bn256::Fr};
+
+fn main() {
+    halo2_utils::info::print_gates::<Fr, FactorisationCircuit<Fr>>();
+}
+
+// output
+//
+// gate 0: product check
+//   constraint 0 (degree 3)
+//     s0 * (advice[1]@cur * advice[1]@next - advice[1]@+2)
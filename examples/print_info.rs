@@ -12,3 +12,6 @@ fn main() {
 // selectors columns: 1
 // gates: 1
 // lookups: 0
+// permutation columns: 2
+// max degree: 3
+// rotations: [0, 1, 2]
@@ -4,5 +4,5 @@ use halo2_utils::{
 
 fn main() {
     let circuit = FactorisationCircuit::<Fr>::default();
-    LayoutPrinter::from(&circuit).print();
+    LayoutPrinter::from(&circuit).print().unwrap();
 }
@@ -0,0 +1,11 @@
+use halo2_utils::{example_circuit::FactorisationCircuit, halo2_proofs::halo2curves::bn256::Fr};
+
+fn main() {
+    let circuit = FactorisationCircuit {
+        a: Fr::from(2),
+        b: Fr::from(3),
+        _marker: std::marker::PhantomData,
+    };
+
+    halo2_utils::assignments_viewer::view(&circuit, None, None).unwrap();
+}
@@ -1,22 +1,38 @@
-use std::{iter, ops::IndexMut};
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Write},
+    iter,
+};
+
+use serde::Serialize;
 
 use crate::halo2_proofs::{
-    dev::{CellValue, MockProver},
-    halo2curves::ff,
-    plonk::{Any, Circuit},
+    dev::{metadata, CellValue, FailureLocation, InstanceValue, MockProver, VerifyFailure},
+    plonk::{self, Any, Circuit, ConstraintSystem},
 };
-use ethers::types::{BigEndianHash, H256, U256};
 
 use crate::{
-    estimate_k, infer_instance::get_number_of_instance_columns, instance_value, FieldExt, RawField,
+    compat::{
+        column_kind, failure_column, failure_region_index, failure_virtual_cell,
+        region_annotations, selector_index, ColumnKind,
+    },
+    error::Error,
+    estimate_k::{mock_prover, mock_prover_with_instance},
+    expression::{column_name, for_each_query, queried_selectors},
+    instance_value,
+    utils::configure,
+    value_format::ValueFormat,
+    RawField,
 };
 
+pub use crate::column_selector::{ColumnSelector, ColumnType};
+
 use tabled::{
     builder::Builder,
     settings::{object::Rows, Alignment, Modify, Style},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Column {
     Advice(usize),
     Fixed(usize),
@@ -24,183 +40,511 @@ pub enum Column {
     Selector(usize),
 }
 
-/// Prints columns which are selected by their name.
-pub fn print<F: FieldExt + ff::PrimeField, C: Circuit<F>>(
+/// Prints columns which are selected by their name, in the given order. Fails if a name does
+/// not match any column.
+pub fn print<F: RawField, C: Circuit<F>>(
     circuit: &C,
     columns_to_print: Vec<&str>,
     k: Option<u32>,
     max_rows: Option<usize>,
-) where
-    F::Repr: Sized + IndexMut<usize>,
-{
-    let k = k.unwrap_or_else(|| estimate_k(circuit));
-    let num_instance = get_number_of_instance_columns::<F, C>(
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
         #[cfg(feature = "circuit-params")]
         circuit,
     );
-    let prover: MockProver<F> = MockProver::run(k, circuit, vec![vec![]; num_instance]).unwrap();
+
+    let columns = get_columns(&prover, &cs);
+    let mut selected = vec![];
+    for col_name in &columns_to_print {
+        match columns.iter().find(|(_, name)| name == col_name) {
+            Some(column) => selected.push(column.clone()),
+            None => return Err(Error::ColumnNotFound(col_name.to_string())),
+        }
+    }
+
+    print_rows(
+        &prover,
+        &selected,
+        max_rows,
+        value_format.unwrap_or_default(),
+    );
+    Ok(())
+}
+
+/// Prints all the columns in the table, or only the ones picked by the selector, see
+/// `ColumnSelector`.
+pub fn print_all<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+    selector: Option<&ColumnSelector>,
+    max_rows: Option<usize>,
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    let prover = mock_prover(circuit, k)?;
+    let value_format = value_format.unwrap_or_default();
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let range = prover.usable_rows();
+
+    let columns = select_columns(&prover, &cs, selector)?;
 
     let mut table = Builder::default();
+    table.set_header(columns.iter().map(|(_, name)| name.clone()));
 
-    let header =
-        iter::once("row".to_string()).chain(columns_to_print.iter().map(|s| s.to_string()));
-    table.set_header(header);
+    let range_end = max_rows
+        .map(|mr| std::cmp::min(mr, range.end))
+        .unwrap_or(range.end);
 
-    let (advice_annotations, fixed_annotations, instance_annotations) = get_annotations(&prover);
+    for row_id in range.start..=range_end {
+        table.push_record(
+            columns
+                .iter()
+                .map(|(column, _)| format_cell(&prover, column, row_id, value_format)),
+        );
+    }
 
-    let mut col_indexes = vec![];
-    'col: for col_name in &columns_to_print {
-        for (i, advice_col_name) in advice_annotations.iter().enumerate() {
-            if advice_col_name.is_some() && col_name == advice_col_name.as_ref().unwrap() {
-                col_indexes.push(Column::Advice(i));
-                continue 'col;
-            }
-        }
-        for (i, fixed_col_name) in fixed_annotations.iter().enumerate() {
-            if fixed_col_name.is_some() && col_name == fixed_col_name.as_ref().unwrap() {
-                col_indexes.push(Column::Fixed(i));
-                continue 'col;
+    print_table(table);
+    Ok(())
+}
+
+/// Prints only the rows of the regions with the given name, and only the columns they touch
+/// among the ones picked by the selector. Rows are labelled with the absolute row and the
+/// offset inside the region. Fails if no region has the name.
+pub fn print_region<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    region_name: &str,
+    k: Option<u32>,
+    selector: Option<&ColumnSelector>,
+    max_rows: Option<usize>,
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    let prover = mock_prover(circuit, k)?;
+    let value_format = value_format.unwrap_or_default();
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let columns = select_columns(&prover, &cs, selector)?;
+
+    let regions: Vec<_> = prover
+        .regions()
+        .iter()
+        .filter(|region| *region.name() == *region_name)
+        .collect();
+    if regions.is_empty() {
+        return Err(Error::RegionNotFound(region_name.to_string()));
+    }
+
+    for region in regions {
+        let (start, end) = match region.rows() {
+            Some(rows) => rows,
+            None => {
+                println!("region '{}' has no rows", region_name);
+                continue;
             }
+        };
+
+        let region_columns: Vec<&(Column, String)> = columns
+            .iter()
+            .filter(|(column, _)| match column {
+                Column::Advice(i) => touches(region.columns(), ColumnKind::Advice, *i),
+                Column::Fixed(i) => touches(region.columns(), ColumnKind::Fixed, *i),
+                Column::Instance(i) => touches(region.columns(), ColumnKind::Instance, *i),
+                Column::Selector(i) => (start..=end).any(|row| prover.selectors()[*i][row]),
+            })
+            .collect();
+
+        let mut table = Builder::default();
+        table.set_header(
+            ["row".to_string(), "offset".to_string()]
+                .into_iter()
+                .chain(region_columns.iter().map(|(_, name)| name.clone())),
+        );
+
+        // `end` is inclusive, `max_rows` caps the number of rows
+        let range_end = max_rows
+            .map(|mr| std::cmp::min(start + mr, end + 1))
+            .unwrap_or(end + 1);
+
+        for row_id in start..range_end {
+            table.push_record(
+                [row_id.to_string(), (row_id - start).to_string()]
+                    .into_iter()
+                    .chain(
+                        region_columns
+                            .iter()
+                            .map(|(column, _)| format_cell(&prover, column, row_id, value_format)),
+                    ),
+            );
         }
-        for (i, instance_col_name) in instance_annotations.iter().enumerate() {
-            if instance_col_name.is_some() && col_name == instance_col_name.as_ref().unwrap() {
-                col_indexes.push(Column::Instance(i));
-                continue 'col;
+
+        println!("region '{}' (rows {}..={})", region_name, start, end);
+        print_table(table);
+    }
+    Ok(())
+}
+
+/// Runs `MockProver::verify` and prints all the columns with the cells involved in each failure
+/// marked with the number of the failure, followed by a legend of the failures. The marked
+/// cells are the cells queried by unsatisfied constraints, unassigned cells used by gates, the
+/// inputs of failing lookups and the cells of broken copy constraints.
+pub fn print_failures<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    k: Option<u32>,
+    max_rows: Option<usize>,
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    let prover = mock_prover_with_instance(circuit, instance, k)?;
+    let value_format = value_format.unwrap_or_default();
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let columns = get_columns(&prover, &cs);
+
+    let failures = prover.verify().err().unwrap_or_default();
+    let mut markers: HashMap<(Column, usize), Vec<usize>> = HashMap::new();
+    for (i, failure) in failures.iter().enumerate() {
+        for cell in failure_cells(&prover, &cs, failure) {
+            let cell_markers = markers.entry(cell).or_default();
+            if !cell_markers.contains(&(i + 1)) {
+                cell_markers.push(i + 1);
             }
         }
-        panic!("assignments_printer: column '{}' not found", col_name);
     }
 
-    // table.push_record(
-    //     iter::once("-".to_string()).chain(col_indexes.iter().map(|c| format!("{:?}", c))),
-    // );
+    let mut table = Builder::default();
+    table.set_header(
+        iter::once("row".to_string()).chain(columns.iter().map(|(_, name)| name.clone())),
+    );
 
     let range = prover.usable_rows();
-
-    let advice = prover.advice();
-    let fixed = prover.fixed();
-    let instance = prover.instance();
-
     let range_end = max_rows
         .map(|mr| std::cmp::min(mr, range.end))
         .unwrap_or(range.end);
 
     for row_id in range.start..=range_end {
-        table.push_record(iter::once(row_id.to_string()).chain(col_indexes.iter().map(
-            |c| match c {
-                Column::Advice(i) => format_cell_value(advice[*i][row_id]),
-                Column::Fixed(i) => format_cell_value(fixed[*i][row_id]),
-                Column::Instance(i) => format_value(instance_value(&instance[*i][row_id])),
-                Column::Selector(_) => unreachable!(),
+        table.push_record(iter::once(row_id.to_string()).chain(columns.iter().map(
+            |(column, _)| {
+                let value = format_cell(&prover, column, row_id, value_format);
+                match markers.get(&(*column, row_id)) {
+                    Some(cell_markers) => {
+                        let cell_markers: Vec<String> =
+                            cell_markers.iter().map(|m| m.to_string()).collect();
+                        format!("{} [{}]", value, cell_markers.join(","))
+                    }
+                    None => value,
+                }
             },
         )));
     }
 
-    let str = table
-        .build()
-        .with(Style::rounded())
-        .with(Modify::new(Rows::new(1..)).with(Alignment::left()))
-        .to_string();
+    print_table(table);
 
-    println!("{}", str);
+    if failures.is_empty() {
+        println!("no failures");
+    }
+    for (i, failure) in failures.iter().enumerate() {
+        println!("[{}] {}", i + 1, failure);
+    }
+    Ok(())
+}
+
+/// Cells of the table involved in a failure, as shown by `print_failures`.
+fn failure_cells<F: RawField>(
+    prover: &MockProver<F>,
+    cs: &ConstraintSystem<F>,
+    failure: &VerifyFailure,
+) -> Vec<(Column, usize)> {
+    let n = num_rows(prover, cs) as i64;
+    let cell = |kind: ColumnKind, index: usize, row: usize, rotation: i32| {
+        // rotations wrap around the table like they do in the prover
+        (
+            table_column(kind, index),
+            (row as i64 + rotation as i64).rem_euclid(n) as usize,
+        )
+    };
+
+    match failure {
+        VerifyFailure::CellNotAssigned {
+            region,
+            column,
+            offset,
+            ..
+        } => match region_start(prover, region) {
+            Some(start) => vec![cell(
+                column_kind(column),
+                column.index(),
+                start,
+                *offset as i32,
+            )],
+            None => vec![],
+        },
+        VerifyFailure::ConstraintNotSatisfied {
+            location,
+            cell_values,
+            ..
+        } => match failure_row(prover, location) {
+            Some(row) => cell_values
+                .iter()
+                .filter_map(|(virtual_cell, _)| failure_virtual_cell(virtual_cell))
+                .map(|(kind, index, rotation)| cell(kind, index, row, rotation))
+                .collect(),
+            None => vec![],
+        },
+        VerifyFailure::Lookup {
+            lookup_index,
+            location,
+            ..
+        } => match failure_row(prover, location) {
+            Some(row) => {
+                let mut cells = vec![];
+                for expr in prover.cs().lookups()[*lookup_index].input_expressions() {
+                    for_each_query(expr, &mut |kind, index, rotation| {
+                        cells.push(cell(kind, index, row, rotation))
+                    });
+                }
+                cells
+            }
+            None => vec![],
+        },
+        VerifyFailure::Permutation { column, location } => {
+            match (failure_column(column), failure_row(prover, location)) {
+                (Some((kind, index)), Some(row)) => vec![cell(kind, index, row, 0)],
+                _ => vec![],
+            }
+        }
+        // poisoned constraints are not tied to a row
+        _ => vec![],
+    }
+}
+
+fn region_start<F: RawField>(prover: &MockProver<F>, region: &metadata::Region) -> Option<usize> {
+    let index = failure_region_index(region)?;
+    prover.regions().get(index)?.rows().map(|(start, _)| start)
+}
+
+fn failure_row<F: RawField>(prover: &MockProver<F>, location: &FailureLocation) -> Option<usize> {
+    match location {
+        FailureLocation::InRegion { region, offset } => {
+            region_start(prover, region).map(|start| start + offset)
+        }
+        FailureLocation::OutsideRegion { row } => Some(*row),
+    }
+}
+
+/// Whether a cell of the table was assigned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellStatus {
+    Assigned,
+    Unassigned,
+    Poisoned,
 }
 
-/// Prints all the columns in the table.
-pub fn print_all<F: RawField, C: Circuit<F>>(circuit: &C, k: Option<u32>, max_rows: Option<usize>)
-where
-    F::Repr: Sized + IndexMut<usize>,
-{
-    let k = k.unwrap_or_else(|| estimate_k(circuit));
+#[derive(Serialize)]
+struct JsonCell<'a> {
+    column: &'a str,
+    status: CellStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonRow<'a> {
+    row: usize,
+    cells: Vec<JsonCell<'a>>,
+}
 
-    let num_instance = get_number_of_instance_columns::<F, C>(
+/// Writes every row of the advice, fixed, selector and instance columns as CSV. Column
+/// annotations are used as headers. Assigned cells hold their value in hex, other cells hold
+/// `unassigned` or `poisoned`. The writer is buffered internally.
+pub fn export_csv<F: RawField, C: Circuit<F>, W: Write>(
+    circuit: &C,
+    k: Option<u32>,
+    writer: W,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(writer);
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
         #[cfg(feature = "circuit-params")]
         circuit,
     );
-    let prover: MockProver<F> = MockProver::run(k, circuit, vec![vec![]; num_instance]).unwrap();
+    let columns = get_columns(&prover, &cs);
+    let names = unique_names(&columns);
+
+    let header: Vec<String> = iter::once("row")
+        .chain(names.iter().map(|name| name.as_str()))
+        .map(csv_field)
+        .collect();
+    writeln!(writer, "{}", header.join(","))?;
+
+    for row_id in 0..num_rows(&prover, &cs) {
+        let record: Vec<String> =
+            iter::once(row_id.to_string())
+                .chain(columns.iter().map(|(column, _)| {
+                    match cell_status(&prover, column, row_id) {
+                        (CellStatus::Assigned, Some(value)) => ValueFormat::Hex.format(value),
+                        (CellStatus::Poisoned, _) => "poisoned".to_string(),
+                        _ => "unassigned".to_string(),
+                    }
+                }))
+                .map(|field| csv_field(&field))
+                .collect();
+        writeln!(writer, "{}", record.join(","))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-    let range = prover.usable_rows();
+/// Writes every row of the advice, fixed, selector and instance columns as line delimited
+/// JSON, one object per row holding the status and the value of each cell. The writer is
+/// buffered internally.
+pub fn export_json<F: RawField, C: Circuit<F>, W: Write>(
+    circuit: &C,
+    k: Option<u32>,
+    writer: W,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(writer);
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let columns = get_columns(&prover, &cs);
+    let names = unique_names(&columns);
+
+    for row_id in 0..num_rows(&prover, &cs) {
+        let cells = columns
+            .iter()
+            .zip(&names)
+            .map(|((column, _), name)| {
+                let (status, value) = cell_status(&prover, column, row_id);
+                JsonCell {
+                    column: name,
+                    status,
+                    value: value.map(|value| ValueFormat::Hex.format(value)),
+                }
+            })
+            .collect();
+        serde_json::to_writer(&mut writer, &JsonRow { row: row_id, cells })?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
 
-    let advice = prover.advice();
-    let fixed = prover.fixed();
-    let instance = prover.instance();
-
-    let (advice_annotations, fixed_annotations, instance_annotations) =
-        // circuit.annotations();
-        get_annotations(&prover);
-
-    let mut header: Vec<&str> = vec![];
-    for (i, _) in advice.iter().enumerate() {
-        // let should_skip = skip.as_ref().map(|skip| skip.contains(&Column::Advice(i)));
-        // if should_skip.unwrap_or(false) {
-        //     continue;
-        // }
-        if let Some(name) = advice_annotations[i] {
-            header.push(name);
-        } else {
-            header.push("unnamed advice");
-        }
+pub(crate) fn cell_status<F: RawField>(
+    prover: &MockProver<F>,
+    column: &Column,
+    row: usize,
+) -> (CellStatus, Option<F>) {
+    let cell_value = |value: CellValue<F>| match value {
+        CellValue::Unassigned => (CellStatus::Unassigned, None),
+        CellValue::Assigned(f) => (CellStatus::Assigned, Some(f)),
+        CellValue::Poison(_) => (CellStatus::Poisoned, None),
+    };
+    match column {
+        Column::Advice(i) => cell_value(prover.advice()[*i][row]),
+        Column::Fixed(i) => cell_value(prover.fixed()[*i][row]),
+        Column::Instance(i) => match prover.instance()[*i][row] {
+            InstanceValue::Assigned(f) => (CellStatus::Assigned, Some(f)),
+            InstanceValue::Padding => (CellStatus::Unassigned, None),
+        },
+        Column::Selector(i) => (
+            CellStatus::Assigned,
+            Some(F::from(prover.selectors()[*i][row] as u64)),
+        ),
     }
-    for (i, _) in fixed.iter().enumerate() {
-        // let should_skip = skip.as_ref().map(|skip| skip.contains(&Column::Fixed(i)));
-        // if should_skip.unwrap_or(false) {
-        //     continue;
-        // }
-        if let Some(name) = fixed_annotations[i] {
-            header.push(name);
-        } else {
-            header.push("unnamed fixed");
-        }
+}
+
+/// Column names with a `#n` suffix added to repeated ones, so that they work as headers.
+fn unique_names(columns: &[(Column, String)]) -> Vec<String> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    columns
+        .iter()
+        .map(|(_, name)| {
+            let count = seen.entry(name.as_str()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                name.clone()
+            } else {
+                format!("{}#{}", name, count)
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn table_column(kind: ColumnKind, index: usize) -> Column {
+    match kind {
+        ColumnKind::Advice => Column::Advice(index),
+        ColumnKind::Fixed => Column::Fixed(index),
+        ColumnKind::Instance => Column::Instance(index),
     }
-    for (i, _) in instance.iter().enumerate() {
-        // let should_skip = skip
-        //     .as_ref()
-        //     .map(|skip| skip.contains(&Column::Instance(i)));
-        // if should_skip.unwrap_or(false) {
-        //     continue;
-        // }
-        if let Some(name) = instance_annotations[i] {
-            header.push(name);
-        } else {
-            header.push("unnamed instance");
-        }
+}
+
+/// Rows of the whole table, including the unusable rows at the end.
+pub(crate) fn num_rows<F: RawField>(prover: &MockProver<F>, cs: &ConstraintSystem<F>) -> usize {
+    prover.usable_rows().end + cs.blinding_factors() + 1
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
+}
+
+pub(crate) fn touches<'a>(
+    region_columns: impl IntoIterator<Item = &'a plonk::Column<Any>>,
+    kind: ColumnKind,
+    index: usize,
+) -> bool {
+    region_columns
+        .into_iter()
+        .any(|column| column_kind(column) == kind && column.index() == index)
+}
 
+/// Prints the usable rows of the columns, labelled with the row.
+fn print_rows<F: RawField>(
+    prover: &MockProver<F>,
+    columns: &[(Column, String)],
+    max_rows: Option<usize>,
+    value_format: ValueFormat,
+) {
     let mut table = Builder::default();
-    table.set_header(header);
+    table.set_header(
+        iter::once("row".to_string()).chain(columns.iter().map(|(_, name)| name.clone())),
+    );
 
+    let range = prover.usable_rows();
     let range_end = max_rows
         .map(|mr| std::cmp::min(mr, range.end))
         .unwrap_or(range.end);
 
     for row_id in range.start..=range_end {
-        let mut row_data = vec![];
-        for col in advice {
-            // let should_skip = skip.as_ref().map(|skip| skip.contains(&Column::Advice(i)));
-            // if should_skip.unwrap_or(false) {
-            //     continue;
-            // }
-            row_data.push(format_cell_value(col[row_id]));
-        }
-        for col in fixed {
-            // let should_skip = skip.as_ref().map(|skip| skip.contains(&Column::Fixed(i)));
-            // if should_skip.unwrap_or(false) {
-            //     continue;
-            // }
-            row_data.push(format_cell_value(col[row_id]));
-        }
-        for col in instance {
-            // let should_skip = skip
-            //     .as_ref()
-            //     .map(|skip| skip.contains(&Column::Instance(i)));
-            // if should_skip.unwrap_or(false) {
-            //     continue;
-            // }
-            row_data.push(format_value(instance_value(&col[row_id])));
-        }
-
-        table.push_record(row_data);
+        table.push_record(
+            iter::once(row_id.to_string()).chain(
+                columns
+                    .iter()
+                    .map(|(column, _)| format_cell(prover, column, row_id, value_format)),
+            ),
+        );
     }
 
+    print_table(table);
+}
+
+fn print_table(table: Builder) {
     let str = table
         .build()
         .with(Style::rounded())
@@ -210,10 +554,30 @@ where
     println!("{}", str);
 }
 
-pub fn format_cell_value<F: RawField>(value: CellValue<F>) -> String {
+pub(crate) fn format_cell<F: RawField>(
+    prover: &MockProver<F>,
+    column: &Column,
+    row: usize,
+    value_format: ValueFormat,
+) -> String {
+    match column {
+        Column::Advice(i) => format_cell_value(prover.advice()[*i][row], value_format),
+        Column::Fixed(i) => format_cell_value(prover.fixed()[*i][row], value_format),
+        Column::Instance(i) => value_format.format(instance_value(&prover.instance()[*i][row])),
+        Column::Selector(i) => {
+            if prover.selectors()[*i][row] {
+                "1".to_string()
+            } else {
+                "0".to_string()
+            }
+        }
+    }
+}
+
+pub fn format_cell_value<F: RawField>(value: CellValue<F>, value_format: ValueFormat) -> String {
     match value {
         CellValue::Unassigned => "Unassigned".to_string(),
-        CellValue::Assigned(f) => format_value(f),
+        CellValue::Assigned(f) => value_format.format(f),
         CellValue::Poison(v) => format!("Poisoned({})", v),
         // CellValue::Rational(n, d) => format!("Rational({},{})", format_value(n), format_value(d)),
     }
@@ -226,43 +590,173 @@ pub fn format_cell_value<F: RawField>(value: CellValue<F>) -> String {
 //     }
 // }
 
-fn format_value<F: RawField>(f: F) -> String {
-    let v = f.to_repr();
-    let v = v.as_ref();
-    let v = U256::from_little_endian(v);
-    if v > U256::from(u64::MAX) {
-        format!("{:?}", H256::from_uint(&v))
-    } else {
-        format!("{:x}", v)
+/// Returns every column of the table with its name, in the order advice, fixed, selectors and
+/// instance. The fixed columns that the `MockProver` appends for compressed selectors are left
+/// out, selectors are shown uncompressed instead.
+/// All the columns, or the ones picked by the selector.
+fn select_columns<F: RawField>(
+    prover: &MockProver<F>,
+    cs: &ConstraintSystem<F>,
+    selector: Option<&ColumnSelector>,
+) -> Result<Vec<(Column, String)>, Error> {
+    let columns = get_columns(prover, cs);
+    match selector {
+        Some(selector) => selector.select(prover, &columns),
+        None => Ok(columns),
     }
 }
 
-#[allow(clippy::type_complexity)]
-fn get_annotations<F: RawField>(
+pub(crate) fn get_columns<F: RawField>(
     prover: &MockProver<F>,
-) -> (Vec<Option<&str>>, Vec<Option<&str>>, Vec<Option<&str>>)
-where
-    F::Repr: Sized + IndexMut<usize>,
-{
-    let advice = prover.advice();
-    let fixed = prover.fixed();
-    let instance = prover.instance();
+    cs: &ConstraintSystem<F>,
+) -> Vec<(Column, String)> {
+    // unannotated columns are named like in the rendered expressions, e.g. `advice[0]`
+    let annotations = region_annotations(prover);
+    let name = |kind: ColumnKind, i: usize| column_name(&annotations, kind, i);
+
+    let mut columns = vec![];
+    for i in 0..cs.num_advice_columns() {
+        columns.push((Column::Advice(i), name(ColumnKind::Advice, i)));
+    }
+    for i in 0..cs.num_fixed_columns() {
+        columns.push((Column::Fixed(i), name(ColumnKind::Fixed, i)));
+    }
+    for (i, selector_name) in get_selector_names(cs).into_iter().enumerate() {
+        columns.push((Column::Selector(i), selector_name));
+    }
+    for i in 0..cs.num_instance_columns() {
+        columns.push((Column::Instance(i), name(ColumnKind::Instance, i)));
+    }
+    columns
+}
 
-    let mut advice_annotations: Vec<Option<&str>> = (0..advice.len()).map(|_| None).collect();
-    let mut fixed_annotations: Vec<Option<&str>> = (0..fixed.len()).map(|_| None).collect();
-    let mut instance_annotations: Vec<Option<&str>> = (0..instance.len()).map(|_| None).collect();
+/// Names each selector after the gates that query it.
+fn get_selector_names<F: RawField>(cs: &ConstraintSystem<F>) -> Vec<String> {
+    let mut names: Vec<Vec<&str>> = vec![vec![]; cs.num_selectors()];
+    for gate in cs.gates() {
+        for poly in gate.polynomials() {
+            for selector in queried_selectors(poly) {
+                let gate_names = match selector_index(&selector).and_then(|i| names.get_mut(i)) {
+                    Some(gate_names) => gate_names,
+                    None => continue,
+                };
+                if !gate_names.contains(&gate.name()) {
+                    gate_names.push(gate.name());
+                }
+            }
+        }
+    }
+    names
+        .into_iter()
+        .map(|gate_names| {
+            if gate_names.is_empty() {
+                "unnamed selector".to_string()
+            } else {
+                gate_names.join(", ")
+            }
+        })
+        .collect()
+}
 
-    let regions = prover.regions();
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        example_circuit::FactorisationCircuit,
+        test_circuits::{LookupCircuit, ProductCircuit},
+    };
+
+    /// Failures of the circuit at k = 4 with the cells each one marks.
+    fn failures<C: Circuit<Fr>>(
+        circuit: &C,
+        instance: Vec<Vec<Fr>>,
+    ) -> Vec<(VerifyFailure, Vec<(Column, usize)>)> {
+        let prover = mock_prover_with_instance(circuit, instance, Some(4)).unwrap();
+        let cs = configure::<Fr, C>(
+            #[cfg(feature = "circuit-params")]
+            circuit,
+        );
+        prover
+            .verify()
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|failure| {
+                let cells = failure_cells(&prover, &cs, &failure);
+                (failure, cells)
+            })
+            .collect()
+    }
 
-    for region in regions {
-        for (col, name) in region.annotations().iter() {
-            match col.column_type() {
-                Any::Advice(_) => advice_annotations[col.index()] = Some(name),
-                Any::Fixed => fixed_annotations[col.index()] = Some(name),
-                Any::Instance => instance_annotations[col.index()] = Some(name),
-            }
+    #[test]
+    fn marks_unsatisfied_constraint() {
+        let circuit = ProductCircuit {
+            a: 3,
+            b: 7,
+            product: Some(20),
+        };
+        let failures = failures(&circuit, vec![]);
+        assert_eq!(failures.len(), 1);
+        let (failure, cells) = &failures[0];
+        assert!(matches!(
+            failure,
+            VerifyFailure::ConstraintNotSatisfied { .. }
+        ));
+        for row in 0..3 {
+            assert!(cells.contains(&(Column::Advice(0), row)), "{:?}", cells);
         }
     }
 
-    (advice_annotations, fixed_annotations, instance_annotations)
+    #[test]
+    fn marks_unassigned_cell() {
+        let circuit = ProductCircuit {
+            a: 3,
+            b: 7,
+            product: None,
+        };
+        let failures = failures(&circuit, vec![]);
+        let (_, cells) = failures
+            .iter()
+            .find(|(failure, _)| matches!(failure, VerifyFailure::CellNotAssigned { .. }))
+            .unwrap();
+        assert_eq!(cells, &vec![(Column::Advice(0), 2)]);
+    }
+
+    #[test]
+    fn marks_lookup_input() {
+        let circuit = LookupCircuit {
+            table_rows: 8,
+            values: vec![1, 50],
+        };
+        let failures = failures(&circuit, vec![]);
+        assert_eq!(failures.len(), 1);
+        let (failure, cells) = &failures[0];
+        assert!(matches!(failure, VerifyFailure::Lookup { .. }));
+        assert!(cells.contains(&(Column::Advice(0), 1)), "{:?}", cells);
+        assert!(!cells.contains(&(Column::Advice(0), 0)), "{:?}", cells);
+    }
+
+    #[test]
+    fn marks_broken_copy_constraint() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        };
+        let failures = failures(&circuit, vec![vec![Fr::from(20)]]);
+        let mut cells: Vec<_> = failures
+            .iter()
+            .inspect(|(failure, _)| assert!(matches!(failure, VerifyFailure::Permutation { .. })))
+            .flat_map(|(_, cells)| cells.clone())
+            .collect();
+        cells.sort_by_key(|(column, row)| (format!("{:?}", column), *row));
+        assert_eq!(
+            cells,
+            vec![(Column::Advice(1), 2), (Column::Instance(0), 0)]
+        );
+    }
 }
@@ -0,0 +1,666 @@
+use std::{cmp, collections::HashMap, io, iter};
+
+use crossterm::{
+    cursor::Show,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+
+use crate::halo2_proofs::{dev::MockProver, plonk::Circuit};
+
+use crate::{
+    assignments_printer::{
+        cell_status, format_cell, get_columns, num_rows, table_column, touches, CellStatus, Column,
+    },
+    compat::{column_kind, ColumnKind},
+    error::Error,
+    estimate_k::mock_prover,
+    instance_value,
+    union_find::UnionFind,
+    utils::configure,
+    value_format::ValueFormat,
+    RawField,
+};
+
+const ROW_LABEL_WIDTH: u16 = 8;
+const COLUMN_WIDTH: u16 = 18;
+/// Copy-constraint peers listed for the selected cell.
+const MAX_PEERS: usize = 8;
+
+const HELP: &str = "arrows/hjkl move, PgUp/PgDn page, g/G first/last row, r region, / search, \
+                    n next match, x hide column, a show all columns, q quit";
+
+/// Opens an interactive view of the assignments in the terminal. Only the rows and columns on
+/// screen are formatted, so it works for tables of any size.
+///
+/// Keys: arrows or `hjkl` move the selection, `PgUp`/`PgDn` scroll by a page, `g`/`G` jump to
+/// the first or last row, `r` jumps to the next region with the given name, `/` searches for a
+/// value written as it is displayed, or for `unassigned` or `poisoned` cells, and `n` repeats
+/// the search, `x` hides the selected column and `a` shows all columns again, `q` quits. The
+/// selected cell's annotation, region and copy-constraint peers are shown below the table, and
+/// its peers are highlighted.
+pub fn view<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let mut viewer = Viewer::new(
+        &prover,
+        get_columns(&prover, &cs),
+        num_rows(&prover, &cs),
+        value_format.unwrap_or_default(),
+    );
+
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    viewer.run(&mut terminal)
+}
+
+/// Keeps the terminal in raw mode on the alternate screen while alive, and restores it when
+/// dropped, also when the viewer returns early with an error or panics.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self, Error> {
+        enable_raw_mode()?;
+        // created before entering the alternate screen, so raw mode is undone if that fails
+        let guard = TerminalGuard;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // nothing left to report errors to
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+    }
+}
+
+/// What `/` looks for, parsed once from the typed text.
+#[derive(Clone, Copy)]
+enum Search<F> {
+    Value(F),
+    Status(CellStatus),
+}
+
+impl<F: RawField> Search<F> {
+    /// Reads the text as `unassigned`, `poisoned` or a value as displayed in the format.
+    fn parse(text: &str, value_format: ValueFormat) -> Option<Self> {
+        let lowercase = text.trim().to_lowercase();
+        if lowercase == "unassigned" {
+            Some(Search::Status(CellStatus::Unassigned))
+        } else if lowercase.starts_with("poisoned") {
+            Some(Search::Status(CellStatus::Poisoned))
+        } else {
+            value_format.parse(text).map(Search::Value)
+        }
+    }
+}
+
+enum Prompt {
+    Region,
+    Search,
+}
+
+struct Viewer<'a, F: RawField> {
+    prover: &'a MockProver<F>,
+    columns: Vec<(Column, String)>,
+    hidden: Vec<bool>,
+    num_rows: usize,
+    value_format: ValueFormat,
+    /// Equivalence class of every cell that is part of a copy constraint.
+    class_of: HashMap<(Column, usize), usize>,
+    classes: Vec<Vec<(Column, usize)>>,
+    /// Selected row.
+    row: usize,
+    /// Index of the selected column in `columns`.
+    column: usize,
+    first_row: usize,
+    /// Position of the first column on screen among the visible columns.
+    first_column: usize,
+    /// Rows that fit on screen, updated on every draw.
+    page_rows: usize,
+    prompt: Option<(Prompt, String)>,
+    last_search: Option<(String, Search<F>)>,
+    message: String,
+}
+
+impl<'a, F: RawField> Viewer<'a, F> {
+    fn new(
+        prover: &'a MockProver<F>,
+        columns: Vec<(Column, String)>,
+        num_rows: usize,
+        value_format: ValueFormat,
+    ) -> Self {
+        let mut union_find = UnionFind::default();
+        for (left_column, left_row, right_column, right_row) in
+            prover.permutation().copy_constraints()
+        {
+            union_find.union(
+                &(
+                    table_column(column_kind(left_column), left_column.index()),
+                    *left_row,
+                ),
+                &(
+                    table_column(column_kind(right_column), right_column.index()),
+                    *right_row,
+                ),
+            );
+        }
+        let classes = union_find.classes();
+        let mut class_of = HashMap::new();
+        for (i, class) in classes.iter().enumerate() {
+            for cell in class {
+                class_of.insert(*cell, i);
+            }
+        }
+
+        Viewer {
+            prover,
+            hidden: vec![false; columns.len()],
+            columns,
+            num_rows,
+            value_format,
+            class_of,
+            classes,
+            row: 0,
+            column: 0,
+            first_row: 0,
+            first_column: 0,
+            page_rows: 1,
+            prompt: None,
+            last_search: None,
+            message: String::new(),
+        }
+    }
+
+    fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), Error> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                // ignore key releases reported on some platforms
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Returns false when the viewer should quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key);
+            return true;
+        }
+
+        self.message.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_row(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_row(1),
+            KeyCode::PageUp => self.move_row(-(self.page_rows as i64)),
+            KeyCode::PageDown => self.move_row(self.page_rows as i64),
+            KeyCode::Home | KeyCode::Char('g') => self.row = 0,
+            KeyCode::End | KeyCode::Char('G') => self.row = self.num_rows - 1,
+            KeyCode::Left | KeyCode::Char('h') => self.move_column(-1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_column(1),
+            KeyCode::Char('r') => self.prompt = Some((Prompt::Region, String::new())),
+            KeyCode::Char('/') => self.prompt = Some((Prompt::Search, String::new())),
+            KeyCode::Char('n') => self.search_next(),
+            KeyCode::Char('x') => {
+                if !self.hide_column() {
+                    self.message = "cannot hide the last column".to_string();
+                }
+            }
+            KeyCode::Char('a') => self.hidden.iter_mut().for_each(|hidden| *hidden = false),
+            _ => {}
+        }
+        true
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => match self.prompt.take() {
+                Some((Prompt::Region, name)) => self.jump_to_region(&name),
+                Some((Prompt::Search, text)) => match Search::parse(&text, self.value_format) {
+                    Some(search) => {
+                        self.last_search = Some((text, search));
+                        self.search_next();
+                    }
+                    None => self.message = format!("cannot search for '{}'", text),
+                },
+                None => {}
+            },
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Backspace => {
+                if let Some((_, input)) = &mut self.prompt {
+                    input.pop();
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some((_, input)) = &mut self.prompt {
+                    input.push(c);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Indexes in `columns` of the columns that are not hidden.
+    fn visible(&self) -> Vec<usize> {
+        (0..self.columns.len())
+            .filter(|i| !self.hidden[*i])
+            .collect()
+    }
+
+    fn move_row(&mut self, delta: i64) {
+        self.row = (self.row as i64 + delta).clamp(0, self.num_rows as i64 - 1) as usize;
+    }
+
+    fn move_column(&mut self, delta: i64) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            return;
+        }
+        let position = visible.iter().position(|i| *i == self.column).unwrap_or(0) as i64;
+        let position = (position + delta).clamp(0, visible.len() as i64 - 1);
+        self.column = visible[position as usize];
+    }
+
+    /// Hides the selected column and selects the next visible one. Returns false when it is the
+    /// last visible column, which stays.
+    fn hide_column(&mut self) -> bool {
+        let visible = self.visible();
+        if visible.len() <= 1 {
+            return false;
+        }
+        self.hidden[self.column] = true;
+        self.column = visible
+            .iter()
+            .find(|i| **i > self.column)
+            .or_else(|| visible.iter().rev().find(|i| **i < self.column))
+            .copied()
+            .unwrap_or(0);
+        true
+    }
+
+    /// Selects the first row of the next region with the given name.
+    fn jump_to_region(&mut self, name: &str) {
+        match self.next_region(name) {
+            Some((start, end)) => {
+                self.row = start;
+                self.message = format!("region '{}' (rows {}..={})", name, start, end);
+            }
+            None => self.message = format!("region '{}' not found", name),
+        }
+    }
+
+    /// Rows of the next region after the selected row with the given name, wrapping around.
+    fn next_region(&self, name: &str) -> Option<(usize, usize)> {
+        let regions: Vec<(usize, usize)> = self
+            .prover
+            .regions()
+            .iter()
+            .filter(|region| *region.name() == *name)
+            .filter_map(|region| region.rows())
+            .collect();
+        regions
+            .iter()
+            .find(|(start, _)| *start > self.row)
+            .or_else(|| regions.first())
+            .copied()
+    }
+
+    /// Selects the next match of the last search.
+    fn search_next(&mut self) {
+        let (text, search) = match &self.last_search {
+            Some((text, search)) => (text.clone(), *search),
+            None => return,
+        };
+        match self.find_next(search) {
+            Some((row, column)) => {
+                self.row = row;
+                self.column = column;
+            }
+            None => self.message = format!("'{}' not found", text),
+        }
+    }
+
+    /// Row and column of the next visible cell after the selected one, row by row and wrapping
+    /// around, that matches. Cells are compared as field elements, nothing is formatted.
+    fn find_next(&self, search: Search<F>) -> Option<(usize, usize)> {
+        let visible = self.visible();
+        let position = visible.iter().position(|i| *i == self.column).unwrap_or(0);
+        let cells = self.num_rows * visible.len();
+        let start = self.row * visible.len() + position;
+        (1..=cells)
+            .map(|step| {
+                let cell = (start + step) % cells;
+                (cell / visible.len(), visible[cell % visible.len()])
+            })
+            .find(|(row, column)| self.matches(&self.columns[*column].0, *row, search))
+    }
+
+    fn matches(&self, column: &Column, row: usize, search: Search<F>) -> bool {
+        match (column, search) {
+            // padding is shown as zero
+            (Column::Instance(i), Search::Value(value)) => {
+                instance_value(&self.prover.instance()[*i][row]) == value
+            }
+            (Column::Instance(_) | Column::Selector(_), Search::Status(_)) => false,
+            (_, Search::Value(value)) => cell_status(self.prover, column, row).1 == Some(value),
+            (_, Search::Status(status)) => cell_status(self.prover, column, row).0 == status,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(4), Constraint::Length(7)])
+            .split(frame.size());
+        self.draw_table(frame, chunks[0]);
+        self.draw_details(frame, chunks[1]);
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        // borders and the header take three lines
+        self.page_rows = cmp::max(area.height.saturating_sub(3) as usize, 1);
+        let page_columns = cmp::max(
+            (area.width.saturating_sub(2 + ROW_LABEL_WIDTH) / (COLUMN_WIDTH + 1)) as usize,
+            1,
+        );
+
+        // scroll so that the selected cell is on screen
+        if self.row < self.first_row {
+            self.first_row = self.row;
+        } else if self.row >= self.first_row + self.page_rows {
+            self.first_row = self.row + 1 - self.page_rows;
+        }
+        let visible = self.visible();
+        let position = visible.iter().position(|i| *i == self.column).unwrap_or(0);
+        if position < self.first_column {
+            self.first_column = position;
+        } else if position >= self.first_column + page_columns {
+            self.first_column = position + 1 - page_columns;
+        }
+        self.first_column = cmp::min(self.first_column, visible.len().saturating_sub(1));
+
+        let shown =
+            &visible[self.first_column..cmp::min(self.first_column + page_columns, visible.len())];
+        let selected = (self.columns[self.column].0, self.row);
+        let selected_class = self.class_of.get(&selected);
+
+        let header = Row::new(
+            iter::once(Cell::from("row".to_string()))
+                .chain(shown.iter().map(|i| Cell::from(self.columns[*i].1.clone()))),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let last_row = cmp::min(self.first_row + self.page_rows, self.num_rows);
+        let rows: Vec<Row> = (self.first_row..last_row)
+            .map(|row| {
+                Row::new(
+                    iter::once(Cell::from(row.to_string())).chain(shown.iter().map(|i| {
+                        let cell = (self.columns[*i].0, row);
+                        let style = if cell == selected {
+                            Style::default().bg(Color::Blue)
+                        } else if selected_class.is_some()
+                            && self.class_of.get(&cell) == selected_class
+                        {
+                            Style::default().fg(Color::Yellow)
+                        } else {
+                            Style::default()
+                        };
+                        Cell::from(format_cell(self.prover, &cell.0, row, self.value_format))
+                            .style(style)
+                    })),
+                )
+            })
+            .collect();
+
+        let widths: Vec<Constraint> = iter::once(Constraint::Length(ROW_LABEL_WIDTH))
+            .chain(shown.iter().map(|_| Constraint::Length(COLUMN_WIDTH)))
+            .collect();
+        let title = format!(
+            " rows {}..{} of {}, {} of {} columns visible ",
+            self.first_row,
+            last_row,
+            self.num_rows,
+            visible.len(),
+            self.columns.len()
+        );
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let (column, name) = &self.columns[self.column];
+        let row = self.row;
+
+        let status = match &self.prompt {
+            Some((Prompt::Region, input)) => format!("region: {}_", input),
+            Some((Prompt::Search, input)) => format!("search: {}_", input),
+            None if !self.message.is_empty() => self.message.clone(),
+            None => HELP.to_string(),
+        };
+
+        let region = self
+            .prover
+            .regions()
+            .iter()
+            .find(|region| match region.rows() {
+                Some((start, end)) if start <= row && row <= end => match column {
+                    Column::Advice(i) => touches(region.columns(), ColumnKind::Advice, *i),
+                    Column::Fixed(i) => touches(region.columns(), ColumnKind::Fixed, *i),
+                    Column::Instance(i) => touches(region.columns(), ColumnKind::Instance, *i),
+                    Column::Selector(_) => true,
+                },
+                _ => false,
+            });
+        let region = match region.and_then(|region| Some((region.name(), region.rows()?.0))) {
+            Some((region_name, start)) => {
+                format!("region: {} (offset {})", region_name, row - start)
+            }
+            None => "region: none".to_string(),
+        };
+
+        let peers = match self.class_of.get(&(*column, row)) {
+            Some(class) => {
+                let peers: Vec<String> = self.classes[*class]
+                    .iter()
+                    .filter(|cell| **cell != (*column, row))
+                    .take(MAX_PEERS)
+                    .map(|(peer_column, peer_row)| {
+                        format!("{}@{}", self.column_name(peer_column), peer_row)
+                    })
+                    .collect();
+                let more = self.classes[*class].len() - 1 - peers.len();
+                if more > 0 {
+                    format!("copy constraints: {} and {} more", peers.join(", "), more)
+                } else {
+                    format!("copy constraints: {}", peers.join(", "))
+                }
+            }
+            None => "copy constraints: none".to_string(),
+        };
+
+        let lines = vec![
+            Line::from(status),
+            Line::from(format!("column: {} ({:?})", name, column)),
+            Line::from(format!(
+                "row: {}, value: {}",
+                row,
+                format_cell(self.prover, column, row, self.value_format)
+            )),
+            Line::from(region),
+            Line::from(peers),
+        ];
+        let details =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" cell "));
+        frame.render_widget(details, area);
+    }
+
+    fn column_name(&self, column: &Column) -> String {
+        self.columns
+            .iter()
+            .find(|(c, _)| c == column)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| format!("{:?}", column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::example_circuit::FactorisationCircuit;
+
+    fn circuit() -> FactorisationCircuit<Fr> {
+        FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        }
+    }
+
+    fn prover() -> MockProver<Fr> {
+        mock_prover(&circuit(), Some(4)).unwrap()
+    }
+
+    fn viewer(prover: &MockProver<Fr>) -> Viewer<Fr> {
+        #[cfg(feature = "circuit-params")]
+        let circuit = circuit();
+        let cs = configure::<Fr, FactorisationCircuit<Fr>>(
+            #[cfg(feature = "circuit-params")]
+            &circuit,
+        );
+        Viewer::new(
+            prover,
+            get_columns(prover, &cs),
+            num_rows(prover, &cs),
+            ValueFormat::Compact,
+        )
+    }
+
+    fn search(viewer: &mut Viewer<Fr>, text: &str) {
+        viewer.handle_key(KeyEvent::from(KeyCode::Char('/')));
+        for c in text.chars() {
+            viewer.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        viewer.handle_key(KeyEvent::from(KeyCode::Enter));
+    }
+
+    #[test]
+    fn parses_searches() {
+        assert!(matches!(
+            Search::<Fr>::parse("21", ValueFormat::Compact),
+            Some(Search::Value(value)) if value == Fr::from(21)
+        ));
+        assert!(matches!(
+            Search::<Fr>::parse(" Unassigned ", ValueFormat::Compact),
+            Some(Search::Status(CellStatus::Unassigned))
+        ));
+        assert!(matches!(
+            Search::<Fr>::parse("poisoned", ValueFormat::Compact),
+            Some(Search::Status(CellStatus::Poisoned))
+        ));
+        assert!(Search::<Fr>::parse("product", ValueFormat::Compact).is_none());
+    }
+
+    #[test]
+    fn finds_values_and_statuses() {
+        let prover = prover();
+        let mut viewer = viewer(&prover);
+        // only the advice columns stay visible
+        viewer.hidden = viewer
+            .columns
+            .iter()
+            .map(|(column, _)| !matches!(column, Column::Advice(_)))
+            .collect();
+
+        assert_eq!(viewer.find_next(Search::Value(Fr::from(21))), Some((2, 1)));
+        assert_eq!(viewer.find_next(Search::Value(Fr::from(22))), None);
+        assert_eq!(
+            viewer.find_next(Search::Status(CellStatus::Unassigned)),
+            Some((1, 0))
+        );
+        assert_eq!(
+            viewer.find_next(Search::Status(CellStatus::Poisoned)),
+            Some((prover.usable_rows().end, 0))
+        );
+
+        // the search starts after the selected cell and wraps around
+        search(&mut viewer, "3");
+        assert_eq!((viewer.row, viewer.column), (0, 1));
+        viewer.handle_key(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!((viewer.row, viewer.column), (0, 1));
+        assert!(viewer.message.is_empty());
+
+        search(&mut viewer, "22");
+        assert_eq!((viewer.row, viewer.column), (0, 1));
+        assert_eq!(viewer.message, "'22' not found");
+    }
+
+    #[test]
+    fn hides_and_shows_columns() {
+        let prover = prover();
+        let mut viewer = viewer(&prover);
+        let num_columns = viewer.columns.len();
+
+        assert!(viewer.hide_column());
+        assert_eq!(viewer.visible(), (1..num_columns).collect::<Vec<_>>());
+        assert_eq!(viewer.column, 1);
+        viewer.move_column(-1);
+        assert_eq!(viewer.column, 1);
+
+        for _ in 2..num_columns {
+            assert!(viewer.hide_column());
+        }
+        assert_eq!(viewer.visible(), vec![num_columns - 1]);
+        assert!(!viewer.hide_column());
+        viewer.handle_key(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(viewer.message, "cannot hide the last column");
+        assert_eq!(viewer.visible(), vec![num_columns - 1]);
+
+        viewer.handle_key(KeyEvent::from(KeyCode::Char('a')));
+        assert_eq!(viewer.visible(), (0..num_columns).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn jumps_to_regions() {
+        let prover = prover();
+        let mut viewer = viewer(&prover);
+        viewer.row = 5;
+
+        assert_eq!(viewer.next_region("region main"), Some((0, 2)));
+        viewer.jump_to_region("region main");
+        assert_eq!(viewer.row, 0);
+        assert_eq!(viewer.message, "region 'region main' (rows 0..=2)");
+
+        assert_eq!(viewer.next_region("no such region"), None);
+        viewer.jump_to_region("no such region");
+        assert_eq!(viewer.row, 0);
+        assert_eq!(viewer.message, "region 'no such region' not found");
+    }
+}
@@ -0,0 +1,257 @@
+use std::{cmp, ops::Range};
+
+use regex::Regex;
+
+use crate::halo2_proofs::dev::MockProver;
+
+use crate::{
+    assignments_printer::{cell_status, CellStatus, Column},
+    error::Error,
+    RawField,
+};
+
+/// Kind of a column of the assignment table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColumnType {
+    Advice,
+    Fixed,
+    Instance,
+    Selector,
+}
+
+impl Column {
+    pub fn column_type(&self) -> ColumnType {
+        match self {
+            Column::Advice(_) => ColumnType::Advice,
+            Column::Fixed(_) => ColumnType::Fixed,
+            Column::Instance(_) => ColumnType::Instance,
+            Column::Selector(_) => ColumnType::Selector,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Column::Advice(i) | Column::Fixed(i) | Column::Instance(i) | Column::Selector(i) => *i,
+        }
+    }
+}
+
+/// Picks columns of the assignment table. A column is selected when it passes each kind of
+/// filter that was set: it has one of the kinds, its name matches one of the names, globs or
+/// regexes, its index is in one of the ranges, and it is touched in the rows. Kinds of filters
+/// that are not set let every column through.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnSelector {
+    kinds: Vec<ColumnType>,
+    names: Vec<String>,
+    globs: Vec<String>,
+    regexes: Vec<String>,
+    indexes: Vec<(ColumnType, Range<usize>)>,
+    touched_rows: Option<Range<usize>>,
+}
+
+impl ColumnSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects the columns of this kind.
+    pub fn kind(mut self, kind: ColumnType) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Selects the column with this exact name, selecting fails if there is no such column.
+    pub fn name(mut self, name: &str) -> Self {
+        self.names.push(name.to_string());
+        self
+    }
+
+    /// Selects the columns with a name matching the glob, where `*` matches any text and `?`
+    /// any single character.
+    pub fn glob(mut self, pattern: &str) -> Self {
+        self.globs.push(pattern.to_string());
+        self
+    }
+
+    /// Selects the columns with a name matching the regex. The regex is compiled when the
+    /// columns are selected.
+    pub fn regex(mut self, pattern: &str) -> Self {
+        self.regexes.push(pattern.to_string());
+        self
+    }
+
+    /// Selects the columns of this kind with an index in the range.
+    pub fn indexes(mut self, kind: ColumnType, range: Range<usize>) -> Self {
+        self.indexes.push((kind, range));
+        self
+    }
+
+    /// Selects the columns with at least one assigned cell in the rows. Selectors count as
+    /// touched on the rows where they are enabled.
+    pub fn touched_in(mut self, rows: Range<usize>) -> Self {
+        self.touched_rows = Some(rows);
+        self
+    }
+
+    /// Returns the selected columns, in the order of the table.
+    pub(crate) fn select<F: RawField>(
+        &self,
+        prover: &MockProver<F>,
+        columns: &[(Column, String)],
+    ) -> Result<Vec<(Column, String)>, Error> {
+        for name in &self.names {
+            if !columns.iter().any(|(_, column_name)| column_name == name) {
+                return Err(Error::ColumnNotFound(name.clone()));
+            }
+        }
+        let regexes = self
+            .regexes
+            .iter()
+            .map(String::as_str)
+            .map(Regex::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let filters_names = !self.names.is_empty() || !self.globs.is_empty() || !regexes.is_empty();
+
+        Ok(columns
+            .iter()
+            .filter(|(column, _)| {
+                self.kinds.is_empty() || self.kinds.contains(&column.column_type())
+            })
+            .filter(|(_, name)| {
+                !filters_names
+                    || self.names.contains(name)
+                    || self.globs.iter().any(|glob| glob_match(glob, name))
+                    || regexes.iter().any(|regex| regex.is_match(name))
+            })
+            .filter(|(column, _)| {
+                self.indexes.is_empty()
+                    || self.indexes.iter().any(|(kind, range)| {
+                        *kind == column.column_type() && range.contains(&column.index())
+                    })
+            })
+            .filter(|(column, _)| match &self.touched_rows {
+                Some(rows) => touched(prover, column, rows.clone()),
+                None => true,
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+fn touched<F: RawField>(prover: &MockProver<F>, column: &Column, rows: Range<usize>) -> bool {
+    let end = cmp::min(rows.end, prover.usable_rows().end);
+    (rows.start..end).any(|row| match column {
+        Column::Selector(i) => prover.selectors()[*i][row],
+        _ => cell_status(prover, column, row).0 == CellStatus::Assigned,
+    })
+}
+
+/// Matches the whole text against a glob with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let the last `*` swallow one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        assignments_printer::get_columns, estimate_k::mock_prover,
+        example_circuit::FactorisationCircuit, utils::configure,
+    };
+
+    #[test]
+    fn glob_matches_whole_name() {
+        assert!(glob_match("advice", "advice"));
+        assert!(!glob_match("advice", "advice colm"));
+        assert!(glob_match("adv*", "advice colm"));
+        assert!(glob_match("*colm", "advice colm"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("a?vice", "advice"));
+        assert!(glob_match("*i*e*", "advice"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("**", "anything"));
+    }
+
+    #[test]
+    fn selects_columns() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(2),
+            b: Fr::from(3),
+            _marker: PhantomData,
+        };
+        let prover = mock_prover(&circuit, Some(4)).unwrap();
+        let cs = configure::<Fr, FactorisationCircuit<Fr>>(
+            #[cfg(feature = "circuit-params")]
+            &circuit,
+        );
+        let columns = get_columns(&prover, &cs);
+        let select = |selector: ColumnSelector| -> Vec<Column> {
+            selector
+                .select(&prover, &columns)
+                .unwrap()
+                .into_iter()
+                .map(|(column, _)| column)
+                .collect()
+        };
+
+        assert_eq!(select(ColumnSelector::new()).len(), columns.len());
+        assert_eq!(
+            select(ColumnSelector::new().kind(ColumnType::Advice)),
+            vec![Column::Advice(0), Column::Advice(1)]
+        );
+        assert_eq!(
+            select(ColumnSelector::new().indexes(ColumnType::Advice, 1..2)),
+            vec![Column::Advice(1)]
+        );
+        // only the second advice column and the selector are assigned in the first rows
+        assert_eq!(
+            select(
+                ColumnSelector::new()
+                    .kind(ColumnType::Advice)
+                    .kind(ColumnType::Selector)
+                    .touched_in(0..3)
+            ),
+            vec![Column::Advice(1), Column::Selector(0)]
+        );
+        assert_eq!(
+            select(ColumnSelector::new().glob("*instance")),
+            vec![Column::Instance(0)]
+        );
+        assert!(matches!(
+            ColumnSelector::new().name("missing").select(&prover, &columns),
+            Err(Error::ColumnNotFound(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            ColumnSelector::new().regex("(").select(&prover, &columns),
+            Err(Error::RegexError(_))
+        ));
+    }
+}
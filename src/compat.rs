@@ -0,0 +1,207 @@
+//! Smooths over the differences between the supported halo2 versions.
+
+use std::{borrow::Borrow, collections::HashMap};
+
+use crate::halo2_proofs::{
+    arithmetic::Field,
+    dev::{metadata, MockProver},
+    plonk::{Any, Column, ConstraintSystem, Selector},
+};
+
+use crate::RawField;
+
+/// Kind of a column, independent of the shape of `Any` in the halo2 version in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ColumnKind {
+    Advice,
+    Fixed,
+    Instance,
+}
+
+pub fn column_kind(column: &Column<Any>) -> ColumnKind {
+    any_kind(column.column_type())
+}
+
+/// Kind of a column type, which plonk columns hand out by reference and the column metadata of
+/// the `MockProver` by value.
+fn any_kind(column_type: impl Borrow<Any>) -> ColumnKind {
+    match column_type.borrow() {
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        Any::Advice(_) => ColumnKind::Advice,
+        #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+        Any::Advice => ColumnKind::Advice,
+        Any::Fixed => ColumnKind::Fixed,
+        Any::Instance => ColumnKind::Instance,
+    }
+}
+
+/// Index of the selector, `None` if it cannot be read in this halo2 version.
+pub fn selector_index(selector: &Selector) -> Option<usize> {
+    #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+    return Some(selector.index());
+    // the index is not exposed in this version, but the derived `Debug` prints it as
+    // `Selector(index, is_simple)`
+    #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+    return format!("{:?}", selector)
+        .strip_prefix("Selector(")?
+        .split(',')
+        .next()
+        .and_then(|index| index.trim().parse().ok());
+}
+
+/// Names given to columns on the constraint system, only available in newer halo2 versions.
+#[cfg_attr(
+    not(any(feature = "v030-halo2", feature = "latest-halo2")),
+    allow(unused_variables)
+)]
+pub fn column_annotations<F: Field>(
+    cs: &ConstraintSystem<F>,
+) -> HashMap<(ColumnKind, usize), String> {
+    #[allow(unused_mut)]
+    let mut annotations = HashMap::new();
+    #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+    for (column, name) in cs.general_column_annotations() {
+        annotations.insert(
+            (any_kind(column.column_type()), column.index()),
+            name.clone(),
+        );
+    }
+    annotations
+}
+
+/// Name of a lookup, older halo2 versions do not name lookups so the index is used.
+#[cfg_attr(
+    not(any(feature = "v030-halo2", feature = "latest-halo2")),
+    allow(unused_variables)
+)]
+pub fn lookup_name<F: Field>(cs: &ConstraintSystem<F>, index: usize) -> String {
+    #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+    return cs.lookups()[index].name().to_string();
+    #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+    return format!("lookup {}", index);
+}
+
+/// Names given to columns with `region.name_column`, only available in newer halo2 versions.
+#[cfg_attr(
+    not(any(feature = "v030-halo2", feature = "latest-halo2")),
+    allow(unused_variables)
+)]
+pub fn region_annotations<F: RawField>(
+    prover: &MockProver<F>,
+) -> HashMap<(ColumnKind, usize), String> {
+    #[allow(unused_mut)]
+    let mut annotations = HashMap::new();
+    #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+    for region in prover.regions() {
+        for (column, name) in region.annotations().iter() {
+            annotations.insert(
+                (any_kind(column.column_type()), column.index()),
+                name.clone(),
+            );
+        }
+    }
+    annotations
+}
+
+// The metadata attached to a `VerifyFailure` keeps its fields private, the functions below read
+// them back from the `Display` output.
+
+/// Index of a region, from `Region {index} ('{name}')`.
+pub fn failure_region_index(region: &metadata::Region) -> Option<usize> {
+    region
+        .to_string()
+        .strip_prefix("Region ")?
+        .split(' ')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Kind and index of a column, from `Column('{kind:?}', {index})`.
+pub fn failure_column(column: &metadata::Column) -> Option<(ColumnKind, usize)> {
+    parse_column(&column.to_string())
+}
+
+/// Kind, index and rotation of a virtual cell, from `{column}@{rotation}` optionally followed
+/// by `({name})`.
+pub fn failure_virtual_cell(cell: &metadata::VirtualCell) -> Option<(ColumnKind, usize, i32)> {
+    let cell = cell.to_string();
+    let (column, rotation) = cell.split_once(")@")?;
+    let (kind, index) = parse_column(column)?;
+    let rotation = rotation.split('(').next()?.parse().ok()?;
+    Some((kind, index, rotation))
+}
+
+fn parse_column(column: &str) -> Option<(ColumnKind, usize)> {
+    let (kind, index) = column.strip_prefix("Column('")?.split_once("', ")?;
+    let kind = if kind.starts_with("Advice") {
+        ColumnKind::Advice
+    } else if kind.starts_with("Fixed") {
+        ColumnKind::Fixed
+    } else if kind.starts_with("Instance") {
+        ColumnKind::Instance
+    } else {
+        return None;
+    };
+    let index = index
+        .trim_end_matches(')')
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+    Some((kind, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+
+    /// Columns of each kind, created the same way in every halo2 version.
+    fn columns() -> [Column<Any>; 3] {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        [
+            cs.advice_column().into(),
+            cs.fixed_column().into(),
+            cs.instance_column().into(),
+        ]
+    }
+
+    #[test]
+    fn reads_failure_region_index() {
+        let region = metadata::Region::from((3, "region main".to_string()));
+        assert_eq!(failure_region_index(&region), Some(3));
+        let region = metadata::Region::from((12, "a region with spaces".to_string()));
+        assert_eq!(failure_region_index(&region), Some(12));
+    }
+
+    #[test]
+    fn reads_failure_column() {
+        let [advice, fixed, instance] = columns();
+        assert_eq!(
+            failure_column(&metadata::Column::from(advice)),
+            Some((ColumnKind::Advice, 0))
+        );
+        assert_eq!(
+            failure_column(&metadata::Column::from(fixed)),
+            Some((ColumnKind::Fixed, 0))
+        );
+        assert_eq!(
+            failure_column(&metadata::Column::from(instance)),
+            Some((ColumnKind::Instance, 0))
+        );
+    }
+
+    #[test]
+    fn reads_failure_virtual_cell() {
+        let [advice, fixed, _] = columns();
+        let cell = metadata::VirtualCell::from((metadata::Column::from(advice), -1));
+        assert_eq!(
+            failure_virtual_cell(&cell),
+            Some((ColumnKind::Advice, 0, -1))
+        );
+        let cell = metadata::VirtualCell::from((metadata::Column::from(fixed), 2, "constant"));
+        assert_eq!(failure_virtual_cell(&cell), Some((ColumnKind::Fixed, 0, 2)));
+    }
+}
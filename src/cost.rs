@@ -0,0 +1,234 @@
+use std::fmt;
+
+use crate::halo2_proofs::plonk::Circuit;
+
+use crate::{utils::configure, RawField};
+
+/// Size of a compressed bn256 G1 point, which is what `RealProver` commits with.
+const POINT_SIZE: usize = 32;
+
+/// Estimated cost of proving and verifying a circuit with KZG and SHPLONK, the setup that
+/// `RealProver` uses.
+///
+/// Instances are not committed to with KZG, so they do not show up here. Selectors are counted
+/// as if each one became its own fixed column, selector compression during keygen can only make
+/// the numbers smaller.
+#[derive(Clone, Debug)]
+pub struct CircuitCost {
+    pub k: u32,
+    /// Max degree over all gate polynomials.
+    pub max_gate_degree: usize,
+    /// Degree of the constraint system, including the permutation and lookup arguments.
+    pub degree: usize,
+    /// Number of `n` sized pieces the quotient polynomial is split into.
+    pub quotient_degree: usize,
+    /// k of the extended domain the quotient polynomial is computed on.
+    pub extended_k: u32,
+    pub advice_commitments: usize,
+    pub lookup_commitments: usize,
+    pub permutation_commitments: usize,
+    pub vanishing_commitments: usize,
+    pub multiopen_commitments: usize,
+    pub advice_evaluations: usize,
+    pub fixed_evaluations: usize,
+    pub permutation_evaluations: usize,
+    pub lookup_evaluations: usize,
+    pub vanishing_evaluations: usize,
+    /// Scalar size in bytes.
+    pub scalar_size: usize,
+    /// Number of terms in the final MSM of the verifier.
+    pub msm_terms: usize,
+    pub pairings: usize,
+}
+
+impl CircuitCost {
+    pub fn extended_domain_size(&self) -> usize {
+        1 << self.extended_k
+    }
+
+    /// Number of points in the proof.
+    pub fn commitments(&self) -> usize {
+        self.advice_commitments
+            + self.lookup_commitments
+            + self.permutation_commitments
+            + self.vanishing_commitments
+            + self.multiopen_commitments
+    }
+
+    /// Number of scalars in the proof.
+    pub fn evaluations(&self) -> usize {
+        self.advice_evaluations
+            + self.fixed_evaluations
+            + self.permutation_evaluations
+            + self.lookup_evaluations
+            + self.vanishing_evaluations
+    }
+
+    /// Proof size in bytes.
+    pub fn proof_size(&self) -> usize {
+        self.commitments() * POINT_SIZE + self.evaluations() * self.scalar_size
+    }
+}
+
+impl fmt::Display for CircuitCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "k: {}", self.k)?;
+        writeln!(f, "max gate degree: {}", self.max_gate_degree)?;
+        writeln!(f, "degree: {}", self.degree)?;
+        writeln!(f, "quotient degree: {}", self.quotient_degree)?;
+        writeln!(
+            f,
+            "extended domain: 2^{} = {}",
+            self.extended_k,
+            self.extended_domain_size()
+        )?;
+        writeln!(f, "commitments: {}", self.commitments())?;
+        writeln!(f, "evaluations: {}", self.evaluations())?;
+        writeln!(f, "proof size: {} bytes", self.proof_size())?;
+        writeln!(f, "verifier msm terms: {}", self.msm_terms)?;
+        write!(f, "verifier pairings: {}", self.pairings)
+    }
+}
+
+/// Estimates the proof size and verifier work of the circuit at the given k.
+pub fn estimate<F: RawField, C: Circuit<F>>(
+    k: u32,
+    #[cfg(feature = "circuit-params")] circuit: &C,
+) -> CircuitCost {
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let max_gate_degree = cs
+        .gates()
+        .iter()
+        .flat_map(|gate| gate.polynomials().iter().map(|poly| poly.degree()))
+        .max()
+        .unwrap_or(0);
+    let degree = cs.degree();
+    let quotient_degree = std::cmp::max(degree, 2) - 1;
+    let extended_k = k + quotient_degree.next_power_of_two().ilog2();
+
+    let num_lookups = cs.lookups().len();
+    let permutation_columns = cs.permutation().get_columns().len();
+    let chunk_len = std::cmp::max(degree, 3) - 2;
+    let permutation_sets = (permutation_columns + chunk_len - 1) / chunk_len;
+
+    let advice_commitments = cs.num_advice_columns();
+    // permuted input, permuted table and product commitments
+    let lookup_commitments = 3 * num_lookups;
+    let permutation_commitments = permutation_sets;
+    // random polynomial and the pieces of the quotient polynomial
+    let vanishing_commitments = 1 + quotient_degree;
+    let multiopen_commitments = 2;
+
+    let advice_evaluations = cs.advice_queries().len();
+    let fixed_evaluations = cs.fixed_queries().len() + cs.num_selectors();
+    // sigma evaluations, and z(x), z(wx) for each set plus z(w^last x) for all but the last
+    let permutation_evaluations = permutation_columns
+        + if permutation_sets > 0 {
+            3 * permutation_sets - 1
+        } else {
+            0
+        };
+    // product, product next, permuted input, permuted input inv and permuted table
+    let lookup_evaluations = 5 * num_lookups;
+    let vanishing_evaluations = 1;
+
+    let fixed_commitments = cs.num_fixed_columns() + cs.num_selectors();
+    let msm_terms = fixed_commitments
+        + permutation_columns
+        + advice_commitments
+        + lookup_commitments
+        + permutation_commitments
+        + vanishing_commitments
+        + multiopen_commitments
+        // generator for the evaluations
+        + 1;
+
+    CircuitCost {
+        k,
+        max_gate_degree,
+        degree,
+        quotient_degree,
+        extended_k,
+        advice_commitments,
+        lookup_commitments,
+        permutation_commitments,
+        vanishing_commitments,
+        multiopen_commitments,
+        advice_evaluations,
+        fixed_evaluations,
+        permutation_evaluations,
+        lookup_evaluations,
+        vanishing_evaluations,
+        scalar_size: F::Repr::default().as_ref().len(),
+        msm_terms,
+        pairings: 2,
+    }
+}
+
+/// Prints the cost of the circuit at the given k.
+pub fn print<F: RawField, C: Circuit<F>>(k: u32, #[cfg(feature = "circuit-params")] circuit: &C) {
+    println!(
+        "{}",
+        estimate::<F, C>(
+            k,
+            #[cfg(feature = "circuit-params")]
+            circuit
+        )
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::example_circuit::FactorisationCircuit;
+
+    #[test]
+    fn factorisation_circuit_cost() {
+        let cost = estimate::<Fr, FactorisationCircuit<Fr>>(
+            4,
+            #[cfg(feature = "circuit-params")]
+            &FactorisationCircuit::default(),
+        );
+
+        assert_eq!(cost.max_gate_degree, 3);
+        assert_eq!(cost.degree, 3);
+        assert_eq!(cost.quotient_degree, 2);
+        assert_eq!(cost.extended_k, 5);
+        assert_eq!(cost.extended_domain_size(), 32);
+        // advice and instance columns have equality enabled, one column per permutation set
+        assert_eq!(cost.permutation_commitments, 2);
+        assert_eq!(cost.permutation_evaluations, 2 + 5);
+        assert_eq!(cost.lookup_commitments, 0);
+        assert_eq!(cost.commitments(), 2 + 2 + 3 + 2);
+        // the product gate queries the advice column at three rotations
+        assert_eq!(cost.advice_evaluations, 3);
+        assert_eq!(cost.fixed_evaluations, 1);
+        assert_eq!(cost.evaluations(), 3 + 1 + 7 + 1);
+        assert_eq!(cost.scalar_size, 32);
+        assert_eq!(cost.proof_size(), (9 + 12) * 32);
+        assert_eq!(cost.msm_terms, 13);
+        assert_eq!(cost.pairings, 2);
+    }
+
+    #[test]
+    fn extended_k_grows_with_k() {
+        let small = estimate::<Fr, FactorisationCircuit<Fr>>(
+            4,
+            #[cfg(feature = "circuit-params")]
+            &FactorisationCircuit::default(),
+        );
+        let large = estimate::<Fr, FactorisationCircuit<Fr>>(
+            10,
+            #[cfg(feature = "circuit-params")]
+            &FactorisationCircuit::default(),
+        );
+        assert_eq!(large.extended_k - small.extended_k, 6);
+        assert_eq!(large.proof_size(), small.proof_size());
+    }
+}
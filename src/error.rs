@@ -1,4 +1,4 @@
-use crate::halo2_proofs::plonk;
+use crate::{halo2_proofs::plonk, infer_instance::InstanceConflict};
 
 #[derive(Debug)]
 pub enum Error {
@@ -6,6 +6,33 @@ pub enum Error {
     StdError(Box<std::io::Error>),
     SerdeJsonError(Box<serde_json::Error>),
     InternalError(&'static str),
+    /// Synthesis still ran out of rows at the largest k that was tried.
+    KTooLarge {
+        max_k: u32,
+    },
+    /// No column of the table has this name.
+    ColumnNotFound(String),
+    /// No region with rows has this name.
+    RegionNotFound(String),
+    /// No gate has this name or index.
+    GateNotFound(String),
+    /// The row is past the last row of the table.
+    RowOutOfRange {
+        row: usize,
+        rows: usize,
+    },
+    /// Instance cells copy constrained to cells assigned different values, one entry for each
+    /// equivalence class.
+    InstanceConflicts(Vec<InstanceConflict>),
+    /// The cell is poisoned, e.g. it is an unusable row.
+    PoisonedCell {
+        column: String,
+        row: usize,
+    },
+    /// Plotters failed to draw the layout.
+    DrawingError(String),
+    ImageError(Box<image::ImageError>),
+    RegexError(Box<regex::Error>),
 }
 
 impl From<plonk::Error> for Error {
@@ -24,3 +51,15 @@ impl From<serde_json::Error> for Error {
         Error::SerdeJsonError(Box::new(err))
     }
 }
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::ImageError(Box::new(err))
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Error::RegexError(Box::new(err))
+    }
+}
@@ -1,29 +1,535 @@
-use crate::halo2_proofs::{dev::MockProver, plonk::Circuit};
+use std::fmt;
 
-use crate::RawField;
+use crate::halo2_proofs::{
+    dev::{CellValue, MockProver},
+    plonk::{self, Circuit},
+};
 
-/// Performs synthesis on a huge plonkish table and then sees how many rows were actually used.
-pub fn estimate_k<F: RawField, C: Circuit<F>>(circuit: &C) -> u32 {
-    let rows = estimate_rows::<F, C>(circuit);
-    rows.ilog2() + 1
+use crate::{
+    compat::{column_kind, ColumnKind},
+    error::Error,
+    expression::queried_columns,
+    utils::{configure, derive_k},
+    CircuitExt, FieldExt, RawField,
+};
+
+/// Largest k that `estimate_k` tries before giving up.
+pub const DEFAULT_MAX_K: u32 = 26;
+
+/// Performs synthesis on growing plonkish tables and then sees how many rows were actually used.
+pub fn estimate_k<F: RawField, C: Circuit<F>>(circuit: &C) -> Result<u32, Error> {
+    estimate_k_with_max(circuit, DEFAULT_MAX_K)
+}
+
+/// What `estimate_k_with_mode` looks at to find the rows used by the circuit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EstimateMode {
+    /// Only the rows of regions, like `estimate_k`.
+    #[default]
+    Regions,
+    /// The maximum over regions, lookup tables, fixed columns, instance columns and
+    /// `cs.minimum_rows()`, see `RowUsageReport`.
+    Full,
+}
+
+/// Same as `estimate_k` but with a custom upper bound on k.
+pub fn estimate_k_with_max<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    max_k: u32,
+) -> Result<u32, Error> {
+    let rows = estimate_rows_with_max(circuit, max_k)?;
+    Ok(k_for_rows(rows as usize))
+}
+
+/// Smallest k whose `2^k` rows hold this many rows.
+fn k_for_rows(rows: usize) -> u32 {
+    rows.next_power_of_two().ilog2()
+}
+
+/// Estimates k using the given mode.
+pub fn estimate_k_with_mode<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    mode: EstimateMode,
+    max_k: u32,
+) -> Result<u32, Error> {
+    match mode {
+        EstimateMode::Regions => estimate_k_with_max(circuit, max_k),
+        EstimateMode::Full => Ok(row_usage_with_max(circuit, max_k)?.k()),
+    }
+}
+
+/// Estimates k in `EstimateMode::Full`, running the circuit with its real instances.
+pub fn estimate_k_ext<F: FieldExt, C: CircuitExt<F>>(circuit: &C) -> Result<u32, Error> {
+    estimate_k_ext_with_max(circuit, DEFAULT_MAX_K)
+}
+
+/// Same as `estimate_k_ext` but with a custom upper bound on k.
+pub fn estimate_k_ext_with_max<F: FieldExt, C: CircuitExt<F>>(
+    circuit: &C,
+    max_k: u32,
+) -> Result<u32, Error> {
+    Ok(row_usage_ext_with_max(circuit, max_k)?.k())
 }
 
-pub fn estimate_rows<F: RawField, C: Circuit<F>>(circuit: &C) -> u32 {
+/// Rows needed by the regions of the circuit and the blinding rows, at least the minimum rows
+/// of the constraint system.
+pub fn estimate_rows<F: RawField, C: Circuit<F>>(circuit: &C) -> Result<u32, Error> {
+    estimate_rows_with_max(circuit, DEFAULT_MAX_K)
+}
+
+pub fn estimate_rows_with_max<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    max_k: u32,
+) -> Result<u32, Error> {
     let num_instance = crate::infer_instance::get_number_of_instance_columns::<F, C>(
         #[cfg(feature = "circuit-params")]
         circuit,
     );
-    let prover = MockProver::run(26, circuit, vec![vec![]; num_instance]).unwrap();
+    let (_, prover) = run_mock_prover(circuit, vec![vec![]; num_instance], max_k)?;
 
-    let mut last_row = 0;
+    let mut used_rows = 0;
     for region in prover.regions() {
         if let Some((start, end)) = region.rows() {
             assert!(end >= start);
-            if end > last_row {
-                last_row = end;
+            used_rows = std::cmp::max(used_rows, end + 1);
+        }
+    }
+
+    let rows = used_rows + prover.cs().blinding_factors() + 1;
+    Ok(std::cmp::max(rows, prover.cs().minimum_rows()) as u32)
+}
+
+/// Runs the `MockProver` at k, or at the smallest k that fits when it is not given.
+pub(crate) fn mock_prover<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+) -> Result<MockProver<F>, Error> {
+    let num_instance = crate::infer_instance::get_number_of_instance_columns::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    mock_prover_with_instance(circuit, vec![vec![]; num_instance], k)
+}
+
+/// Same as `mock_prover` but with the given instances.
+pub(crate) fn mock_prover_with_instance<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    k: Option<u32>,
+) -> Result<MockProver<F>, Error> {
+    match k {
+        Some(k) => Ok(MockProver::run(k, circuit, instance)?),
+        None => Ok(run_mock_prover(circuit, instance, DEFAULT_MAX_K)?.1),
+    }
+}
+
+/// Runs the `MockProver` starting from the smallest k allowed by the constraint system and
+/// grows k until synthesis and the instances stop running out of rows. Returns the k that
+/// worked.
+pub(crate) fn run_mock_prover<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    max_k: u32,
+) -> Result<(u32, MockProver<F>), Error> {
+    let mut k = derive_k::<F, C>();
+    while k <= max_k {
+        match MockProver::run(k, circuit, instance.clone()) {
+            Ok(prover) => return Ok((k, prover)),
+            Err(plonk::Error::NotEnoughRowsAvailable { .. })
+            | Err(plonk::Error::InstanceTooLarge) => k += 1,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(Error::KTooLarge { max_k })
+}
+
+/// Rows of a region, `end` is inclusive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowSpan {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl RowSpan {
+    pub fn rows(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+/// The part of the circuit that needs the most rows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RowUsageSource {
+    Region(String),
+    Table(String),
+    Fixed,
+    Instance(usize),
+    MinimumRows,
+}
+
+impl fmt::Display for RowUsageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowUsageSource::Region(name) => write!(f, "region '{}'", name),
+            RowUsageSource::Table(name) => write!(f, "table '{}'", name),
+            RowUsageSource::Fixed => write!(f, "fixed assignments"),
+            RowUsageSource::Instance(i) => write!(f, "instance column {}", i),
+            RowUsageSource::MinimumRows => write!(f, "minimum rows of the constraint system"),
+        }
+    }
+}
+
+/// Breakdown of the rows used by a circuit, collected from a `MockProver` run.
+#[derive(Clone, Debug)]
+pub struct RowUsageReport {
+    /// Regions assigned through `assign_region`.
+    pub regions: Vec<RowSpan>,
+    /// Regions assigned through `assign_table`, they only touch lookup table columns.
+    pub tables: Vec<RowSpan>,
+    /// Rows used by fixed columns, including constants assigned outside of regions. Lookup
+    /// table columns are left out as they are padded till the last usable row.
+    pub fixed_rows: usize,
+    /// Length of each instance column.
+    pub instance_rows: Vec<usize>,
+    /// Rows reserved at the end of the table for blinding factors.
+    pub blinding_rows: usize,
+    /// `cs.minimum_rows()` of the constraint system.
+    pub minimum_rows: usize,
+}
+
+impl RowUsageReport {
+    /// Rows used by the circuit, excluding the blinding rows.
+    pub fn used_rows(&self) -> usize {
+        self.sources()
+            .into_iter()
+            .filter(|(source, _)| *source != RowUsageSource::MinimumRows)
+            .map(|(_, rows)| rows)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Rows needed for the circuit to fit in the plonkish table.
+    pub fn rows(&self) -> usize {
+        std::cmp::max(self.used_rows() + self.blinding_rows, self.minimum_rows)
+    }
+
+    /// Smallest k that fits all of the rows.
+    pub fn k(&self) -> u32 {
+        k_for_rows(self.rows())
+    }
+
+    /// The component that sets k, the first one in the report on ties.
+    pub fn bottleneck(&self) -> RowUsageSource {
+        let mut bottleneck = (RowUsageSource::MinimumRows, self.minimum_rows);
+        for (source, rows) in self.sources() {
+            if source != RowUsageSource::MinimumRows && rows + self.blinding_rows > bottleneck.1 {
+                bottleneck = (source, rows + self.blinding_rows);
             }
         }
+        bottleneck.0
     }
 
-    (last_row + prover.cs().blinding_factors() + 1) as u32
+    fn sources(&self) -> Vec<(RowUsageSource, usize)> {
+        let mut sources = vec![(RowUsageSource::MinimumRows, self.minimum_rows)];
+        for region in &self.regions {
+            sources.push((RowUsageSource::Region(region.name.clone()), region.end + 1));
+        }
+        for table in &self.tables {
+            sources.push((RowUsageSource::Table(table.name.clone()), table.end + 1));
+        }
+        sources.push((RowUsageSource::Fixed, self.fixed_rows));
+        for (i, rows) in self.instance_rows.iter().enumerate() {
+            sources.push((RowUsageSource::Instance(i), *rows));
+        }
+        sources
+    }
+}
+
+impl fmt::Display for RowUsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "regions: {}", self.regions.len())?;
+        for region in &self.regions {
+            writeln!(
+                f,
+                "  {}: rows {}..={} ({} rows)",
+                region.name,
+                region.start,
+                region.end,
+                region.rows()
+            )?;
+        }
+        writeln!(f, "tables: {}", self.tables.len())?;
+        for table in &self.tables {
+            writeln!(
+                f,
+                "  {}: rows {}..={} ({} rows)",
+                table.name,
+                table.start,
+                table.end,
+                table.rows()
+            )?;
+        }
+        writeln!(f, "fixed rows: {}", self.fixed_rows)?;
+        writeln!(f, "instance rows: {:?}", self.instance_rows)?;
+        writeln!(f, "blinding rows: {}", self.blinding_rows)?;
+        writeln!(f, "minimum rows: {}", self.minimum_rows)?;
+        write!(
+            f,
+            "rows: {} (k={}, set by {})",
+            self.rows(),
+            self.k(),
+            self.bottleneck()
+        )
+    }
+}
+
+/// Reports which regions, tables, fixed and instance columns use the rows of the circuit.
+pub fn row_usage<F: RawField, C: Circuit<F>>(circuit: &C) -> Result<RowUsageReport, Error> {
+    row_usage_with_max(circuit, DEFAULT_MAX_K)
+}
+
+/// Same as `row_usage` but with a custom upper bound on k.
+pub fn row_usage_with_max<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    max_k: u32,
+) -> Result<RowUsageReport, Error> {
+    let num_instance = crate::infer_instance::get_number_of_instance_columns::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    collect_row_usage(circuit, vec![vec![]; num_instance], max_k)
+}
+
+/// Same as `row_usage` but passes the real instances of the circuit to the `MockProver`.
+pub fn row_usage_ext<F: FieldExt, C: CircuitExt<F>>(circuit: &C) -> Result<RowUsageReport, Error> {
+    row_usage_ext_with_max(circuit, DEFAULT_MAX_K)
+}
+
+/// Same as `row_usage_ext` but with a custom upper bound on k.
+pub fn row_usage_ext_with_max<F: FieldExt, C: CircuitExt<F>>(
+    circuit: &C,
+    max_k: u32,
+) -> Result<RowUsageReport, Error> {
+    collect_row_usage(circuit, circuit.instances(), max_k)
+}
+
+fn collect_row_usage<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    max_k: u32,
+) -> Result<RowUsageReport, Error> {
+    let instance_rows = instance.iter().map(|column| column.len()).collect();
+    let (_, prover) = run_mock_prover(circuit, instance, max_k)?;
+
+    // the prover's constraint system has selectors compressed into extra fixed columns
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let table_columns: Vec<usize> = queried_columns(
+        cs.lookups()
+            .iter()
+            .flat_map(|lookup| lookup.table_expressions()),
+    )
+    .into_iter()
+    .filter(|(kind, _)| *kind == ColumnKind::Fixed)
+    .map(|(_, index)| index)
+    .collect();
+
+    let mut regions = vec![];
+    let mut tables = vec![];
+    for region in prover.regions() {
+        if let Some((start, end)) = region.rows() {
+            let span = RowSpan {
+                name: region.name().to_string(),
+                start,
+                end,
+            };
+            // `assign_table` only assigns table columns, regions that only enable selectors
+            // have no columns at all
+            let is_table = !region.columns().is_empty()
+                && region.columns().iter().all(|column| {
+                    column_kind(column) == ColumnKind::Fixed
+                        && table_columns.contains(&column.index())
+                });
+            if is_table {
+                tables.push(span);
+            } else {
+                regions.push(span);
+            }
+        }
+    }
+
+    let usable_rows = prover.usable_rows().clone();
+    let fixed_rows = prover
+        .fixed()
+        .iter()
+        .take(cs.num_fixed_columns())
+        .enumerate()
+        .filter(|(i, _)| !table_columns.contains(i))
+        .filter_map(|(_, column)| {
+            column[usable_rows.clone()]
+                .iter()
+                .rposition(|cell| !matches!(cell, CellValue::Unassigned))
+        })
+        .map(|row| row + 1)
+        .max()
+        .unwrap_or(0);
+
+    Ok(RowUsageReport {
+        regions,
+        tables,
+        fixed_rows,
+        instance_rows,
+        blinding_rows: cs.blinding_factors() + 1,
+        minimum_rows: cs.minimum_rows(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{
+        example_circuit::FactorisationCircuit,
+        test_circuits::{LookupCircuit, RowsCircuit},
+    };
+
+    fn span(name: &str, start: usize, end: usize) -> RowSpan {
+        RowSpan {
+            name: name.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn derives_k_from_minimum_rows() {
+        // 5 blinding factors, l_last, l_0 and one row make 8 minimum rows
+        assert_eq!(derive_k::<Fr, FactorisationCircuit<Fr>>(), 4);
+        assert_eq!(derive_k::<Fr, RowsCircuit>(), 4);
+    }
+
+    #[test]
+    fn estimates_factorisation_circuit() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        };
+        assert_eq!(estimate_k(&circuit).unwrap(), 4);
+    }
+
+    #[test]
+    fn grows_k_until_synthesis_fits() {
+        // 20 rows and 6 blinding rows do not fit in the 16 rows of k = 4
+        let circuit = RowsCircuit { rows: 20 };
+        let (k, prover) = run_mock_prover(&circuit, vec![], DEFAULT_MAX_K).unwrap();
+        assert_eq!(k, 5);
+        assert_eq!(prover.usable_rows().end, 26);
+    }
+
+    #[test]
+    fn fails_above_max_k() {
+        let circuit = RowsCircuit { rows: 20 };
+        assert!(matches!(
+            run_mock_prover(&circuit, vec![], 4),
+            Err(Error::KTooLarge { max_k: 4 })
+        ));
+        assert!(matches!(
+            estimate_k_with_max(&circuit, 4),
+            Err(Error::KTooLarge { max_k: 4 })
+        ));
+    }
+
+    #[test]
+    fn reports_factorisation_circuit() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        };
+        let report = row_usage(&circuit).unwrap();
+        assert_eq!(report.regions, vec![span("region main", 0, 2)]);
+        assert_eq!(report.tables, vec![]);
+        assert_eq!(report.fixed_rows, 0);
+        assert_eq!(report.instance_rows, vec![0]);
+        assert_eq!(report.blinding_rows, 6);
+        assert_eq!(report.minimum_rows, 8);
+        assert_eq!(report.used_rows(), 3);
+        assert_eq!(report.rows(), 9);
+        assert_eq!(report.k(), 4);
+        assert_eq!(
+            report.bottleneck(),
+            RowUsageSource::Region("region main".to_string())
+        );
+    }
+
+    #[test]
+    fn separates_tables_from_regions() {
+        let circuit = LookupCircuit {
+            table_rows: 40,
+            values: vec![3, 5, 39],
+        };
+        let report = row_usage(&circuit).unwrap();
+        assert_eq!(report.tables, vec![span("range table", 0, 39)]);
+        assert!(report.regions.contains(&span("values", 0, 2)));
+        assert!(report
+            .regions
+            .iter()
+            .all(|region| region.name != "range table"));
+        assert_eq!(
+            report.bottleneck(),
+            RowUsageSource::Table("range table".to_string())
+        );
+        assert_eq!(report.k(), 6);
+    }
+
+    #[test]
+    fn modes_agree_on_k() {
+        // 10 rows and 6 blinding rows fill k = 4 exactly
+        for rows in [1, 2, 9, 10, 11, 26, 27] {
+            let circuit = RowsCircuit { rows };
+            let k = estimate_k(&circuit).unwrap();
+            assert_eq!(
+                estimate_k_with_mode(&circuit, EstimateMode::Full, DEFAULT_MAX_K).unwrap(),
+                k,
+                "{} rows",
+                rows
+            );
+            assert_eq!(k, k_for_rows(rows + 6).max(3), "{} rows", rows);
+        }
+        assert_eq!(estimate_k(&RowsCircuit { rows: 10 }).unwrap(), 4);
+        assert_eq!(estimate_k(&RowsCircuit { rows: 11 }).unwrap(), 5);
+    }
+
+    #[test]
+    fn bottleneck_prefers_the_first_on_ties() {
+        let mut report = RowUsageReport {
+            regions: vec![span("first", 0, 3), span("second", 1, 3)],
+            tables: vec![span("table", 0, 3)],
+            fixed_rows: 4,
+            instance_rows: vec![4],
+            blinding_rows: 6,
+            minimum_rows: 8,
+        };
+        assert_eq!(report.rows(), 10);
+        assert_eq!(report.k(), 4);
+        assert_eq!(
+            report.bottleneck(),
+            RowUsageSource::Region("first".to_string())
+        );
+
+        // the minimum rows come first
+        report.minimum_rows = 10;
+        assert_eq!(report.bottleneck(), RowUsageSource::MinimumRows);
+
+        report.minimum_rows = 8;
+        report.instance_rows = vec![4, 5];
+        assert_eq!(report.bottleneck(), RowUsageSource::Instance(1));
+        assert_eq!(report.used_rows(), 5);
+    }
 }
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use crate::halo2_proofs::{
+    arithmetic::Field,
+    plonk::{Expression, Selector},
+};
+
+use crate::{
+    compat::{selector_index, ColumnKind},
+    value_format::ValueFormat,
+    RawField,
+};
+
+/// Returns the column kind, column index and rotation if the expression is a column query.
+pub(crate) fn as_query<F: Field>(expr: &Expression<F>) -> Option<(ColumnKind, usize, i32)> {
+    match expr {
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        Expression::Fixed(q) => Some((ColumnKind::Fixed, q.column_index(), q.rotation().0)),
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        Expression::Advice(q) => Some((ColumnKind::Advice, q.column_index(), q.rotation().0)),
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        Expression::Instance(q) => Some((ColumnKind::Instance, q.column_index(), q.rotation().0)),
+        #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+        Expression::Fixed {
+            column_index,
+            rotation,
+            ..
+        } => Some((ColumnKind::Fixed, *column_index, rotation.0)),
+        #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+        Expression::Advice {
+            column_index,
+            rotation,
+            ..
+        } => Some((ColumnKind::Advice, *column_index, rotation.0)),
+        #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+        Expression::Instance {
+            column_index,
+            rotation,
+            ..
+        } => Some((ColumnKind::Instance, *column_index, rotation.0)),
+        _ => None,
+    }
+}
+
+/// Calls `query` for every column query found in the expression, with the column kind, the
+/// column index and the rotation.
+pub(crate) fn for_each_query<F: Field>(
+    expr: &Expression<F>,
+    query: &mut impl FnMut(ColumnKind, usize, i32),
+) {
+    match expr {
+        Expression::Negated(a) | Expression::Scaled(a, _) => for_each_query(a, query),
+        Expression::Sum(a, b) | Expression::Product(a, b) => {
+            for_each_query(a, query);
+            for_each_query(b, query);
+        }
+        _ => {
+            if let Some((kind, index, rotation)) = as_query(expr) {
+                query(kind, index, rotation)
+            }
+        }
+    }
+}
+
+/// Returns the distinct columns queried by the expressions, in the order they are first seen.
+pub(crate) fn queried_columns<'a, F: Field + 'a>(
+    exprs: impl IntoIterator<Item = &'a Expression<F>>,
+) -> Vec<(ColumnKind, usize)> {
+    let mut columns = vec![];
+    for expr in exprs {
+        for_each_query(expr, &mut |kind, index, _| {
+            if !columns.contains(&(kind, index)) {
+                columns.push((kind, index));
+            }
+        });
+    }
+    columns
+}
+
+/// Name of a column, its annotation if there is one.
+pub(crate) fn column_name(
+    annotations: &HashMap<(ColumnKind, usize), String>,
+    kind: ColumnKind,
+    index: usize,
+) -> String {
+    match annotations.get(&(kind, index)) {
+        Some(name) => name.clone(),
+        None => match kind {
+            ColumnKind::Advice => format!("advice[{}]", index),
+            ColumnKind::Fixed => format!("fixed[{}]", index),
+            ColumnKind::Instance => format!("instance[{}]", index),
+        },
+    }
+}
+
+pub(crate) fn format_rotation(rotation: i32) -> String {
+    match rotation {
+        0 => "cur".to_string(),
+        1 => "next".to_string(),
+        -1 => "prev".to_string(),
+        r if r > 0 => format!("+{}", r),
+        r => r.to_string(),
+    }
+}
+
+/// Renders the expression as readable algebra, e.g. `s0 * (advice[1]@cur - advice[1]@next)`.
+pub(crate) fn render<F: RawField>(
+    expr: &Expression<F>,
+    annotations: &HashMap<(ColumnKind, usize), String>,
+) -> String {
+    let operands: Vec<String> = operands(expr)
+        .into_iter()
+        .map(|operand| render(operand, annotations))
+        .collect();
+    let operands: Vec<&str> = operands.iter().map(String::as_str).collect();
+    render_node(expr, annotations, &operands)
+}
+
+/// Operands of the expression as they are rendered, `a` and `b` for `a - b`.
+fn operands<F: Field>(expr: &Expression<F>) -> Vec<&Expression<F>> {
+    match expr {
+        Expression::Negated(a) | Expression::Scaled(a, _) => vec![a.as_ref()],
+        Expression::Sum(a, b) => match b.as_ref() {
+            Expression::Negated(b) => vec![a.as_ref(), b.as_ref()],
+            b => vec![a.as_ref(), b],
+        },
+        Expression::Product(a, b) => vec![a.as_ref(), b.as_ref()],
+        _ => vec![],
+    }
+}
+
+/// Renders the expression from its already rendered operands, in the order `render` renders
+/// them, so a tree of subexpressions can be rendered without rendering each one again.
+pub(crate) fn render_node<F: RawField>(
+    expr: &Expression<F>,
+    annotations: &HashMap<(ColumnKind, usize), String>,
+    operands: &[&str],
+) -> String {
+    match expr {
+        Expression::Constant(c) => ValueFormat::Auto.format(*c),
+        Expression::Selector(s) => match selector_index(s) {
+            Some(index) => format!("s{}", index),
+            None => format!("{:?}", s),
+        },
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        Expression::Challenge(c) => format!("challenge[{}]", c.index()),
+        Expression::Negated(a) => format!("-{}", wrap(a, operands[0], 2)),
+        Expression::Sum(_, b) => match b.as_ref() {
+            Expression::Negated(b) => format!("{} - {}", operands[0], wrap(b, operands[1], 1)),
+            _ => format!("{} + {}", operands[0], operands[1]),
+        },
+        Expression::Product(a, b) => {
+            format!("{} * {}", wrap(a, operands[0], 1), wrap(b, operands[1], 1))
+        }
+        Expression::Scaled(a, f) => format!(
+            "{} * {}",
+            wrap(a, operands[0], 1),
+            ValueFormat::Auto.format(*f)
+        ),
+        _ => {
+            let (kind, index, rotation) = as_query(expr).expect("column query");
+            format!(
+                "{}@{}",
+                column_name(annotations, kind, index),
+                format_rotation(rotation)
+            )
+        }
+    }
+}
+
+/// Puts the rendered expression in parentheses if it binds looser than `precedence`.
+fn wrap<F: Field>(expr: &Expression<F>, rendered: &str, precedence: u8) -> String {
+    let expr_precedence = match expr {
+        Expression::Sum(..) => 0,
+        Expression::Product(..) | Expression::Scaled(..) => 1,
+        _ => 2,
+    };
+    if expr_precedence < precedence {
+        format!("({})", rendered)
+    } else {
+        rendered.to_string()
+    }
+}
+
+/// Degree of the lookup argument, mirrors halo2's `lookup::Argument::required_degree`.
+pub(crate) fn lookup_degree<F: Field>(
+    input_expressions: &[Expression<F>],
+    table_expressions: &[Expression<F>],
+) -> usize {
+    let input_degree = input_expressions
+        .iter()
+        .map(|expr| expr.degree())
+        .fold(1, std::cmp::max);
+    let table_degree = table_expressions
+        .iter()
+        .map(|expr| expr.degree())
+        .fold(1, std::cmp::max);
+    std::cmp::max(4, 2 + input_degree + table_degree)
+}
+
+/// Returns the distinct selectors queried by the expression.
+pub(crate) fn queried_selectors<F: Field>(expr: &Expression<F>) -> Vec<Selector> {
+    fn walk<F: Field>(expr: &Expression<F>, selectors: &mut Vec<Selector>) {
+        match expr {
+            Expression::Selector(s) => {
+                if !selectors.contains(s) {
+                    selectors.push(*s);
+                }
+            }
+            Expression::Negated(a) | Expression::Scaled(a, _) => walk(a, selectors),
+            Expression::Sum(a, b) | Expression::Product(a, b) => {
+                walk(a, selectors);
+                walk(b, selectors);
+            }
+            _ => {}
+        }
+    }
+
+    let mut selectors = vec![];
+    walk(expr, &mut selectors);
+    selectors
+}
@@ -1,4 +1,7 @@
-use halo2_proofs_v2022_08_19::{arithmetic::Group, halo2curves::bn256::Fr};
+use halo2_proofs_v2022_08_19::{
+    arithmetic::Group,
+    halo2curves::{bn256::Fr, pasta},
+};
 
 #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
 use crate::halo2_proofs::halo2curves::ff::FromUniformBytes;
@@ -11,6 +14,8 @@ pub trait RawField: crate::halo2_proofs::arithmetic::Field + FromUniformBytes<64
 #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
 pub trait RawField: crate::halo2_proofs::arithmetic::FieldExt + Group + From<u64> + Ord {}
 impl RawField for Fr {}
+#[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+impl RawField for pasta::Fp {}
 
 #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
 impl RawField for Fp {}
@@ -18,6 +23,8 @@ impl RawField for Fp {}
 pub trait FieldExt: RawField + From<u64> {}
 
 impl FieldExt for Fr {}
+#[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+impl FieldExt for pasta::Fp {}
 
 #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
 impl FieldExt for Fp {}
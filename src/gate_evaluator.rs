@@ -0,0 +1,438 @@
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+
+use crate::halo2_proofs::{
+    dev::{CellValue, MockProver},
+    plonk::{Circuit, Expression},
+};
+
+use crate::{
+    assignments_printer::num_rows,
+    compat::{column_annotations, selector_index, ColumnKind},
+    error::Error,
+    estimate_k::mock_prover_with_instance,
+    expression::{as_query, render_node},
+    instance_value,
+    utils::configure,
+    value_format::ValueFormat,
+    RawField,
+};
+
+/// A gate, picked by its name or by its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateRef<'a> {
+    Name(&'a str),
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for GateRef<'a> {
+    fn from(name: &'a str) -> Self {
+        GateRef::Name(name)
+    }
+}
+
+impl From<usize> for GateRef<'_> {
+    fn from(index: usize) -> Self {
+        GateRef::Index(index)
+    }
+}
+
+/// A subexpression of a constraint with its value at the evaluated row.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EvaluatedTerm {
+    pub expression: String,
+    /// The formatted value, `poisoned` if the term depends on a poisoned cell and `unknown` if
+    /// it depends on a challenge or on a selector whose index cannot be read. Unassigned cells
+    /// evaluate to zero like in the `MockProver`.
+    pub value: String,
+    /// Operands of the term, empty for constants, selectors and cells.
+    pub terms: Vec<EvaluatedTerm>,
+}
+
+/// A polynomial constraint of a gate evaluated at a row.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EvaluatedConstraint {
+    pub index: usize,
+    pub name: String,
+    /// Whether the constraint evaluates to zero, `None` if the value is poisoned or unknown.
+    pub satisfied: Option<bool>,
+    pub term: EvaluatedTerm,
+}
+
+/// Every constraint of a gate evaluated at a row, with the value of each subterm.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GateEvaluation {
+    pub gate: String,
+    pub index: usize,
+    pub row: usize,
+    pub constraints: Vec<EvaluatedConstraint>,
+}
+
+impl fmt::Display for GateEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gate {} '{}' at row {}", self.index, self.gate, self.row)?;
+        for constraint in &self.constraints {
+            let status = match constraint.satisfied {
+                Some(true) => "satisfied",
+                Some(false) => "not satisfied",
+                None => "unknown",
+            };
+            writeln!(f)?;
+            if constraint.name.is_empty() {
+                writeln!(f, "constraint {}: {}", constraint.index, status)?;
+            } else {
+                writeln!(
+                    f,
+                    "constraint {} '{}': {}",
+                    constraint.index, constraint.name, status
+                )?;
+            }
+            write!(
+                f,
+                "{} = {}",
+                constraint.term.expression, constraint.term.value
+            )?;
+            write_terms(f, &constraint.term.terms, "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the operands below their term as a tree.
+fn write_terms(f: &mut fmt::Formatter<'_>, terms: &[EvaluatedTerm], prefix: &str) -> fmt::Result {
+    for (i, term) in terms.iter().enumerate() {
+        let last = i + 1 == terms.len();
+        write!(
+            f,
+            "\n{}{} {} = {}",
+            prefix,
+            if last { "└─" } else { "├─" },
+            term.expression,
+            term.value
+        )?;
+        let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        write_terms(f, &term.terms, &prefix)?;
+    }
+    Ok(())
+}
+
+/// Evaluates each polynomial of the gate at the row against the values of a `MockProver` run
+/// with the given instances, at k or at an estimated k.
+pub fn evaluate_gate<'a, F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    gate: impl Into<GateRef<'a>>,
+    row: usize,
+    k: Option<u32>,
+    value_format: Option<ValueFormat>,
+) -> Result<GateEvaluation, Error> {
+    let prover = mock_prover_with_instance(circuit, instance, k)?;
+    // the gates of the prover's constraint system have their selectors compressed into fixed
+    // columns, the gates of a fresh one still query the selectors
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let gate_ref = gate.into();
+    let (index, gate) = cs
+        .gates()
+        .iter()
+        .enumerate()
+        .find(|(i, gate)| match gate_ref {
+            GateRef::Name(name) => gate.name() == name,
+            GateRef::Index(index) => *i == index,
+        })
+        .ok_or_else(|| {
+            Error::GateNotFound(match gate_ref {
+                GateRef::Name(name) => name.to_string(),
+                GateRef::Index(index) => index.to_string(),
+            })
+        })?;
+
+    let rows = num_rows(&prover, &cs);
+    if row >= rows {
+        return Err(Error::RowOutOfRange { row, rows });
+    }
+
+    let evaluator = Evaluator {
+        prover: &prover,
+        row,
+        rows,
+        annotations: column_annotations(&cs),
+        value_format: value_format.unwrap_or_default(),
+    };
+    let constraints = gate
+        .polynomials()
+        .iter()
+        .enumerate()
+        .map(|(i, poly)| {
+            let (value, term) = evaluator.evaluate(poly);
+            EvaluatedConstraint {
+                index: i,
+                name: gate.constraint_name(i).to_string(),
+                satisfied: match value {
+                    Value::Known(value) => Some(value == F::from(0u64)),
+                    _ => None,
+                },
+                term,
+            }
+        })
+        .collect();
+
+    Ok(GateEvaluation {
+        gate: gate.name().to_string(),
+        index,
+        row,
+        constraints,
+    })
+}
+
+/// Prints the evaluation of the gate at the row, see `evaluate_gate`.
+pub fn print_gate_evaluation<'a, F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    instance: Vec<Vec<F>>,
+    gate: impl Into<GateRef<'a>>,
+    row: usize,
+    k: Option<u32>,
+    value_format: Option<ValueFormat>,
+) -> Result<(), Error> {
+    println!(
+        "{}",
+        evaluate_gate(circuit, instance, gate, row, k, value_format)?
+    );
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Value<F> {
+    Known(F),
+    Poisoned,
+    Unknown,
+}
+
+impl<F: RawField> Value<F> {
+    fn map(self, f: impl FnOnce(F) -> F) -> Self {
+        match self {
+            Value::Known(a) => Value::Known(f(a)),
+            other => other,
+        }
+    }
+
+    fn zip_with(self, other: Self, f: impl FnOnce(F, F) -> F) -> Self {
+        match (self, other) {
+            (Value::Known(a), Value::Known(b)) => Value::Known(f(a, b)),
+            (Value::Poisoned, _) | (_, Value::Poisoned) => Value::Poisoned,
+            _ => Value::Unknown,
+        }
+    }
+}
+
+struct Evaluator<'a, F: RawField> {
+    prover: &'a MockProver<F>,
+    row: usize,
+    rows: usize,
+    annotations: HashMap<(ColumnKind, usize), String>,
+    value_format: ValueFormat,
+}
+
+impl<F: RawField> Evaluator<'_, F> {
+    /// Returns the value of the expression at the row, with the tree of its evaluated terms.
+    fn evaluate(&self, expr: &Expression<F>) -> (Value<F>, EvaluatedTerm) {
+        let mut note = None;
+        let (value, terms) = match expr {
+            Expression::Constant(c) => (Value::Known(*c), vec![]),
+            Expression::Selector(s) => match selector_index(s) {
+                Some(index) => {
+                    let enabled = self.prover.selectors()[index][self.row];
+                    (Value::Known(F::from(enabled as u64)), vec![])
+                }
+                None => (Value::Unknown, vec![]),
+            },
+            #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+            Expression::Challenge(_) => (Value::Unknown, vec![]),
+            Expression::Negated(a) => {
+                let (a, a_term) = self.evaluate(a);
+                (a.map(|a| -a), vec![a_term])
+            }
+            Expression::Sum(a, b) => {
+                let (a, a_term) = self.evaluate(a);
+                match b.as_ref() {
+                    // rendered as `a - b`, so `b` is the operand shown
+                    Expression::Negated(b) => {
+                        let (b, b_term) = self.evaluate(b);
+                        (a.zip_with(b, |a, b| a - b), vec![a_term, b_term])
+                    }
+                    _ => {
+                        let (b, b_term) = self.evaluate(b);
+                        (a.zip_with(b, |a, b| a + b), vec![a_term, b_term])
+                    }
+                }
+            }
+            Expression::Product(a, b) => {
+                let (a, a_term) = self.evaluate(a);
+                let (b, b_term) = self.evaluate(b);
+                (a.zip_with(b, |a, b| a * b), vec![a_term, b_term])
+            }
+            Expression::Scaled(a, f) => {
+                let (a, a_term) = self.evaluate(a);
+                (a.map(|a| a * *f), vec![a_term])
+            }
+            _ => {
+                let (kind, index, rotation) = as_query(expr).expect("column query");
+                // rotations wrap around the table like they do in the prover
+                let row = (self.row as i64 + rotation as i64).rem_euclid(self.rows as i64) as usize;
+                let cell_value = match kind {
+                    ColumnKind::Advice => self.prover.advice()[index][row],
+                    ColumnKind::Fixed => self.prover.fixed()[index][row],
+                    ColumnKind::Instance => {
+                        CellValue::Assigned(instance_value(&self.prover.instance()[index][row]))
+                    }
+                };
+                let value = match cell_value {
+                    CellValue::Unassigned => {
+                        note = Some("unassigned");
+                        Value::Known(F::from(0u64))
+                    }
+                    CellValue::Assigned(f) => Value::Known(f),
+                    CellValue::Poison(_) => Value::Poisoned,
+                };
+                (value, vec![])
+            }
+        };
+
+        let formatted = match value {
+            Value::Known(f) => match note {
+                Some(note) => format!("{} ({})", self.value_format.format(f), note),
+                None => self.value_format.format(f),
+            },
+            Value::Poisoned => "poisoned".to_string(),
+            Value::Unknown => "unknown".to_string(),
+        };
+        // the operands are already rendered in their terms
+        let operands: Vec<&str> = terms.iter().map(|term| term.expression.as_str()).collect();
+        let term = EvaluatedTerm {
+            expression: render_node(expr, &self.annotations, &operands),
+            value: formatted,
+            terms,
+        };
+        (value, term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{example_circuit::FactorisationCircuit, test_circuits::ProductCircuit};
+
+    fn circuit() -> FactorisationCircuit<Fr> {
+        FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        }
+    }
+
+    fn evaluate<'a>(gate: impl Into<GateRef<'a>>, row: usize) -> Result<GateEvaluation, Error> {
+        evaluate_gate(
+            &circuit(),
+            vec![vec![Fr::from(21)]],
+            gate,
+            row,
+            Some(4),
+            Some(ValueFormat::Decimal),
+        )
+    }
+
+    #[test]
+    fn evaluates_satisfied_row() {
+        let evaluation = evaluate("product check", 0).unwrap();
+        assert_eq!((evaluation.index, evaluation.row), (0, 0));
+        assert_eq!(evaluation.constraints.len(), 1);
+        let constraint = &evaluation.constraints[0];
+        assert_eq!(constraint.satisfied, Some(true));
+        assert_eq!(constraint.term.value, "0");
+
+        // the terms are rendered from their operands like the whole expression
+        #[cfg(feature = "circuit-params")]
+        let circuit = circuit();
+        let cs = configure::<Fr, FactorisationCircuit<Fr>>(
+            #[cfg(feature = "circuit-params")]
+            &circuit,
+        );
+        assert_eq!(
+            constraint.term.expression,
+            crate::expression::render(&cs.gates()[0].polynomials()[0], &column_annotations(&cs))
+        );
+        assert_eq!(evaluate(0, 0).unwrap(), evaluation);
+    }
+
+    #[test]
+    fn evaluates_tampered_row() {
+        let circuit = ProductCircuit {
+            a: 3,
+            b: 7,
+            product: Some(20),
+        };
+        let evaluation = evaluate_gate(
+            &circuit,
+            vec![],
+            "product check",
+            0,
+            Some(4),
+            Some(ValueFormat::Decimal),
+        )
+        .unwrap();
+        let term = &evaluation.constraints[0].term;
+        assert_eq!(evaluation.constraints[0].satisfied, Some(false));
+        assert_eq!(
+            term.expression,
+            "s0 * (advice[0]@cur * advice[0]@next - advice[0]@+2)"
+        );
+        assert_eq!(term.value, "1");
+
+        let difference = &term.terms[1];
+        assert_eq!(
+            difference.expression,
+            "advice[0]@cur * advice[0]@next - advice[0]@+2"
+        );
+        let values: Vec<(&str, &str)> = difference
+            .terms
+            .iter()
+            .map(|term| (term.expression.as_str(), term.value.as_str()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("advice[0]@cur * advice[0]@next", "21"),
+                ("advice[0]@+2", "20")
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_on_unknown_gate() {
+        assert!(matches!(
+            evaluate("no such gate", 0),
+            Err(Error::GateNotFound(name)) if name == "no such gate"
+        ));
+        assert!(matches!(
+            evaluate(1, 0),
+            Err(Error::GateNotFound(name)) if name == "1"
+        ));
+    }
+
+    #[test]
+    fn fails_on_row_out_of_range() {
+        assert!(evaluate("product check", 15).is_ok());
+        assert!(matches!(
+            evaluate("product check", 16),
+            Err(Error::RowOutOfRange { row: 16, rows: 16 })
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+use std::{cmp, collections::HashMap, fmt::Debug, io::Write, ops::Range};
+
+use serde::Serialize;
+
+use crate::halo2_proofs::plonk::Circuit;
+
+use crate::{
+    assignments_printer::{cell_status, get_columns, num_rows, table_column, CellStatus, Column},
+    compat::column_kind,
+    derive_circuit_name,
+    error::Error,
+    estimate_k::mock_prover,
+    union_find::UnionFind,
+    utils::configure,
+    value_format::ValueFormat,
+    RawField,
+};
+
+/// Page with the drawing and the hover and click handlers, `__DATA__` is replaced with the
+/// layout as JSON.
+const TEMPLATE: &str = include_str!("html_layout.html");
+
+/// Part of the layout to export, everything when nothing is set. Columns are numbered like
+/// in the page, instance columns first, then advice, fixed and selector columns.
+#[derive(Clone, Debug, Default)]
+pub struct Viewport {
+    rows: Option<Range<usize>>,
+    columns: Option<Range<usize>>,
+    region: Option<String>,
+}
+
+impl Viewport {
+    /// Exports only these rows.
+    pub fn rows(mut self, rows: Range<usize>) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Exports only these columns.
+    pub fn columns(mut self, columns: Range<usize>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Exports only the rows and columns of the first region with this name. Ranges set with
+    /// `rows` and `columns` take precedence.
+    pub fn region(mut self, name: &str) -> Self {
+        self.region = Some(name.to_string());
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct HtmlColumn {
+    name: String,
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct HtmlRegion {
+    name: String,
+    start: usize,
+    /// Inclusive.
+    end: usize,
+    columns: Vec<usize>,
+}
+
+/// Cells joined by copy constraints.
+#[derive(Serialize)]
+struct HtmlClass {
+    /// Number of cells in the class, also counting those out of view.
+    size: usize,
+    /// Cells in view, as column and row.
+    cells: Vec<(usize, usize)>,
+}
+
+/// Columns are numbered from the first column in view, rows are the rows of the table.
+#[derive(Serialize)]
+struct HtmlLayout {
+    title: String,
+    /// Rows in view, `end` excluded.
+    start: usize,
+    end: usize,
+    usable_rows: usize,
+    columns: Vec<HtmlColumn>,
+    regions: Vec<HtmlRegion>,
+    /// Assigned cells as column, row and formatted value. Selectors are listed where enabled.
+    cells: Vec<(usize, usize, String)>,
+    classes: Vec<HtmlClass>,
+}
+
+/// Writes a self-contained HTML page with the layout of the circuit, laid out like
+/// `CircuitLayout` with instance, advice, fixed and selector columns from left to right.
+/// Hovering a cell shows its column, region, offset in the region and value, clicking a cell
+/// highlights every cell of its copy-constraint equivalence class. Only the cells in the
+/// viewport are written and drawn.
+pub fn export<F: RawField, C: Circuit<F> + Debug, W: Write>(
+    circuit: &C,
+    k: Option<u32>,
+    value_format: Option<ValueFormat>,
+    viewport: &Viewport,
+    mut writer: W,
+) -> Result<(), Error> {
+    let value_format = value_format.unwrap_or_default();
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let rows = num_rows(&prover, &cs);
+
+    let mut columns = get_columns(&prover, &cs);
+    columns.sort_by_key(|(column, _)| match column {
+        Column::Instance(_) => 0,
+        Column::Advice(_) => 1,
+        Column::Fixed(_) => 2,
+        Column::Selector(_) => 3,
+    });
+    let position: HashMap<Column, usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (column, _))| (*column, i))
+        .collect();
+
+    let mut regions = vec![];
+    for region in prover.regions() {
+        let (start, end) = match region.rows() {
+            Some(rows) => rows,
+            None => continue,
+        };
+        let mut region_columns: Vec<usize> = region
+            .columns()
+            .iter()
+            .filter_map(|column| position.get(&table_column(column_kind(column), column.index())))
+            .copied()
+            .collect();
+        for (i, enabled) in prover.selectors().iter().enumerate() {
+            if enabled[start..=end].iter().any(|enabled| *enabled) {
+                region_columns.push(position[&Column::Selector(i)]);
+            }
+        }
+        region_columns.sort_unstable();
+        regions.push(HtmlRegion {
+            name: region.name().to_string(),
+            start,
+            end,
+            columns: region_columns,
+        });
+    }
+
+    let (mut view_rows, mut view_columns) = (0..rows, 0..columns.len());
+    if let Some(name) = &viewport.region {
+        let region = regions
+            .iter()
+            .find(|region| region.name == *name)
+            .ok_or_else(|| Error::RegionNotFound(name.to_string()))?;
+        view_rows = region.start..region.end + 1;
+        if let (Some(first), Some(last)) = (region.columns.first(), region.columns.last()) {
+            view_columns = *first..*last + 1;
+        }
+    }
+    if let Some(rows) = &viewport.rows {
+        view_rows = rows.clone();
+    }
+    if let Some(columns) = &viewport.columns {
+        view_columns = columns.clone();
+    }
+    let view_rows = cmp::min(view_rows.start, rows)..cmp::min(view_rows.end, rows);
+    let view_columns =
+        cmp::min(view_columns.start, columns.len())..cmp::min(view_columns.end, columns.len());
+    // column in the page of a column of the table, if it is in view
+    let in_view = |column: usize| {
+        view_columns
+            .contains(&column)
+            .then(|| column - view_columns.start)
+    };
+
+    let regions: Vec<HtmlRegion> = regions
+        .into_iter()
+        .filter(|region| region.start < view_rows.end && region.end >= view_rows.start)
+        .map(|region| HtmlRegion {
+            columns: region.columns.iter().filter_map(|c| in_view(*c)).collect(),
+            ..region
+        })
+        .filter(|region| !region.columns.is_empty())
+        .collect();
+
+    let mut cells = vec![];
+    for (i, (column, _)) in columns.iter().enumerate() {
+        let i = match in_view(i) {
+            Some(i) => i,
+            None => continue,
+        };
+        for row in view_rows.clone() {
+            match (column, cell_status(&prover, column, row)) {
+                (Column::Selector(_), (_, Some(value))) if value == F::from(0u64) => {}
+                (_, (CellStatus::Assigned, Some(value))) => {
+                    cells.push((i, row, value_format.format(value)))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut classes = UnionFind::default();
+    for (left_column, left_row, right_column, right_row) in prover.permutation().copy_constraints()
+    {
+        let left = table_column(column_kind(left_column), left_column.index());
+        let right = table_column(column_kind(right_column), right_column.index());
+        if let (Some(left), Some(right)) = (position.get(&left), position.get(&right)) {
+            classes.union(&(*left, *left_row), &(*right, *right_row));
+        }
+    }
+
+    let classes = classes
+        .classes()
+        .into_iter()
+        .map(|class| HtmlClass {
+            size: class.len(),
+            cells: class
+                .into_iter()
+                .filter(|(_, row)| view_rows.contains(row))
+                .filter_map(|(column, row)| Some((in_view(column)?, row)))
+                .collect(),
+        })
+        .filter(|class| !class.cells.is_empty())
+        .collect();
+
+    let mut title = format!(
+        "{} Layout (k={})",
+        derive_circuit_name(circuit),
+        rows.ilog2()
+    );
+    if view_rows != (0..rows) {
+        title = format!("{} rows {}..{}", title, view_rows.start, view_rows.end);
+    }
+    let layout = HtmlLayout {
+        title,
+        start: view_rows.start,
+        end: view_rows.end,
+        usable_rows: prover.usable_rows().end,
+        columns: columns
+            .into_iter()
+            .skip(view_columns.start)
+            .take(view_columns.len())
+            .map(|(column, name)| HtmlColumn {
+                name,
+                kind: match column {
+                    Column::Advice(_) => "advice",
+                    Column::Fixed(_) => "fixed",
+                    Column::Instance(_) => "instance",
+                    Column::Selector(_) => "selector",
+                },
+            })
+            .collect(),
+        regions,
+        cells,
+        classes,
+    };
+
+    // `</` would end the script element early
+    let data = serde_json::to_string(&layout)?.replace("</", "<\\/");
+    writer.write_all(TEMPLATE.replace("__DATA__", &data).as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use serde_json::{json, Value};
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{example_circuit::FactorisationCircuit, test_circuits::RegionsCircuit};
+
+    fn circuit() -> FactorisationCircuit<Fr> {
+        FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        }
+    }
+
+    fn page<C: Circuit<Fr> + Debug>(circuit: &C, viewport: &Viewport) -> Result<String, Error> {
+        let mut page = vec![];
+        export(
+            circuit,
+            Some(4),
+            Some(ValueFormat::Decimal),
+            viewport,
+            &mut page,
+        )?;
+        Ok(String::from_utf8(page).unwrap())
+    }
+
+    /// The layout embedded in the page.
+    fn layout(page: &str) -> Value {
+        let data = page.split("const data = ").nth(1).unwrap();
+        let data = data.lines().next().unwrap().trim_end_matches(';');
+        serde_json::from_str(data).unwrap()
+    }
+
+    /// Cells of the copy-constraint classes, sorted, with the size of each class.
+    fn classes(layout: &Value) -> Vec<(u64, Vec<(u64, u64)>)> {
+        layout["classes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|class| {
+                let mut cells: Vec<(u64, u64)> =
+                    serde_json::from_value(class["cells"].clone()).unwrap();
+                cells.sort_unstable();
+                (class["size"].as_u64().unwrap(), cells)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exports_layout() {
+        let layout = layout(&page(&circuit(), &Viewport::default()).unwrap());
+
+        let kinds: Vec<&str> = layout["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|column| column["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, vec!["instance", "advice", "advice", "selector"]);
+        assert_eq!(layout["columns"][0]["name"], "instance[0]");
+        assert_eq!(layout["columns"][1]["name"], "advice[0]");
+
+        assert_eq!((&layout["start"], &layout["end"]), (&json!(0), &json!(16)));
+        assert_eq!(
+            layout["regions"],
+            json!([{ "name": "region main", "start": 0, "end": 2, "columns": [2, 3] }])
+        );
+        assert_eq!(
+            layout["cells"],
+            json!([[2, 0, "3"], [2, 1, "7"], [2, 2, "21"], [3, 0, "1"]])
+        );
+        // the product is copied to the instance
+        assert_eq!(classes(&layout), vec![(2, vec![(0, 0), (2, 2)])]);
+    }
+
+    #[test]
+    fn clips_to_viewport() {
+        let layout_of = |viewport: Viewport| layout(&page(&circuit(), &viewport).unwrap());
+
+        let layout = layout_of(Viewport::default().rows(1..3));
+        assert_eq!((&layout["start"], &layout["end"]), (&json!(1), &json!(3)));
+        assert_eq!(layout["cells"], json!([[2, 1, "7"], [2, 2, "21"]]));
+        assert_eq!(classes(&layout), vec![(2, vec![(2, 2)])]);
+
+        // columns are numbered from the first column in view
+        let layout = layout_of(Viewport::default().columns(2..4));
+        assert_eq!(layout["columns"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            layout["cells"],
+            json!([[0, 0, "3"], [0, 1, "7"], [0, 2, "21"], [1, 0, "1"]])
+        );
+        assert_eq!(layout["regions"][0]["columns"], json!([0, 1]));
+        assert_eq!(classes(&layout), vec![(2, vec![(0, 2)])]);
+
+        let layout = layout_of(Viewport::default().columns(0..2));
+        assert_eq!(layout["cells"], json!([]));
+        assert_eq!(layout["regions"], json!([]));
+        assert_eq!(classes(&layout), vec![(2, vec![(0, 0)])]);
+
+        let layout = layout_of(Viewport::default().region("region main"));
+        assert_eq!((&layout["start"], &layout["end"]), (&json!(0), &json!(3)));
+        assert_eq!(layout["columns"].as_array().unwrap().len(), 2);
+        assert_eq!(layout["cells"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn fails_on_unknown_region() {
+        assert!(matches!(
+            page(&circuit(), &Viewport::default().region("no such region")),
+            Err(Error::RegionNotFound(name)) if name == "no such region"
+        ));
+    }
+
+    #[test]
+    fn escapes_script_end() {
+        let circuit = RegionsCircuit {
+            regions: vec![("</script><b>", 0, 1)],
+        };
+        let page = page(&circuit, &Viewport::default()).unwrap();
+        assert!(page.contains(r#""<\/script><b>""#));
+        assert_eq!(
+            page.matches("</script>").count(),
+            TEMPLATE.matches("</script>").count()
+        );
+        assert_eq!(layout(&page)["regions"][0]["name"], "</script><b>");
+    }
+}
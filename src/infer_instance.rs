@@ -1,9 +1,20 @@
-use crate::halo2_proofs::dev::MockProver;
-use crate::halo2_proofs::plonk::Any;
+use std::{collections::HashMap, fmt};
+
+use crate::halo2_proofs::dev::CellValue;
 
 use crate::halo2_proofs::plonk::{Circuit, ConstraintSystem};
 
-use crate::{estimate_k, parse_cell_value, RawField};
+use crate::{
+    compat::{column_annotations, column_kind, ColumnKind},
+    error::Error,
+    estimate_k::mock_prover,
+    expression::column_name,
+    permutation_printer::CellRef,
+    union_find::UnionFind,
+    utils::configure,
+    value_format::ValueFormat,
+    RawField,
+};
 
 #[cfg_attr(
     not(feature = "circuit-params"),
@@ -21,54 +32,434 @@ pub fn get_number_of_instance_columns<F: RawField, C: Circuit<F>>(
     cs.num_instance_columns()
 }
 
+/// An instance cell whose inferred value may not be the one the circuit expects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstanceWarning {
+    /// No copy constraint reaches the cell, it is set to zero. Only cells above the last
+    /// reached row of their column are reported.
+    Unreached(CellRef),
+    /// No cell copy constrained to this one, directly or through other cells, is assigned, it
+    /// is set to zero. `class` holds the other cells of its equivalence class.
+    NoAssignedSource { cell: CellRef, class: Vec<CellRef> },
+}
+
+impl fmt::Display for InstanceWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceWarning::Unreached(cell) => {
+                write!(f, "{} is not reached by any copy constraint", cell)
+            }
+            InstanceWarning::NoAssignedSource { cell, class } => {
+                let class: Vec<String> = class.iter().map(|cell| cell.to_string()).collect();
+                write!(
+                    f,
+                    "{} is only copy constrained to cells without a value: {}",
+                    cell,
+                    class.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Instance cells of one equivalence class whose assigned cells hold different values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceConflict {
+    /// Instance cells of the class, none of them can be inferred.
+    pub cells: Vec<CellRef>,
+    /// One assigned cell for each distinct value, with the value.
+    pub sources: Vec<(CellRef, String)>,
+}
+
+impl fmt::Display for InstanceConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<String> = self.cells.iter().map(|cell| cell.to_string()).collect();
+        let sources: Vec<String> = self
+            .sources
+            .iter()
+            .map(|(cell, value)| format!("{} = {}", cell, value))
+            .collect();
+        write!(
+            f,
+            "{} copy constrained to different values: {}",
+            cells.join(", "),
+            sources.join(", ")
+        )
+    }
+}
+
+/// Instances inferred from the copy constraints, with the cells they may be wrong for.
+#[derive(Clone, Debug)]
+pub struct InferredInstance<F> {
+    pub instance: Vec<Vec<F>>,
+    pub warnings: Vec<InstanceWarning>,
+}
+
 // Finds the instances for the circuit using copy constraints.
-pub fn infer_instance<F: RawField, C: Circuit<F>>(circuit: &C, k: Option<u32>) -> Vec<Vec<F>> {
-    let k = k.unwrap_or_else(|| estimate_k(circuit));
-    let num_instance = get_number_of_instance_columns::<F, C>(
+pub fn infer_instance<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+) -> Result<Vec<Vec<F>>, Error> {
+    Ok(infer_instance_with_warnings(circuit, k)?.instance)
+}
+
+/// Same as `infer_instance`, also returning the instance cells whose value could not be
+/// inferred from an assigned cell.
+///
+/// Copy constraints are followed transitively: every instance cell takes the value of the
+/// assigned advice and fixed cells of its equivalence class, so chains through other instance
+/// cells and unassigned cells are resolved. Classes whose assigned cells hold different values
+/// are all reported together as `Error::InstanceConflicts`, before any class that only has
+/// poisoned cells is reported as `Error::PoisonedCell`.
+pub fn infer_instance_with_warnings<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+) -> Result<InferredInstance<F>, Error> {
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
         #[cfg(feature = "circuit-params")]
         circuit,
     );
-    let instance = vec![vec![]; num_instance];
-    let prover: MockProver<F> = MockProver::run(k, circuit, instance).unwrap();
-    let copy_constraints = prover.permutation().copy_constraints();
-
-    let mut instance: Vec<Vec<F>> = vec![vec![]; num_instance];
-    for (left_column, left_row, right_column, right_row) in copy_constraints {
-        let is_left_instance = matches!(left_column.column_type(), Any::Instance);
-        let is_right_instance = matches!(right_column.column_type(), Any::Instance);
-
-        assert!(
-            !is_left_instance || !is_right_instance,
-            "both should not be instance"
-        );
+    let copies = prover.permutation().copy_constraints().iter().map(
+        |(left_column, left_row, right_column, right_row)| {
+            (
+                (column_kind(left_column), left_column.index(), *left_row),
+                (column_kind(right_column), right_column.index(), *right_row),
+            )
+        },
+    );
+    infer(
+        cs.num_instance_columns(),
+        copies,
+        |(kind, index, row)| match kind {
+            ColumnKind::Advice => prover.advice()[index][row],
+            ColumnKind::Fixed => prover.fixed()[index][row],
+            ColumnKind::Instance => CellValue::Unassigned,
+        },
+        &column_annotations(&cs),
+    )
+}
+
+/// A cell as kind and index of its column, and row.
+type Cell = (ColumnKind, usize, usize);
+
+/// Infers the instance columns from the copy constraints and the values of the advice and
+/// fixed cells.
+fn infer<F: RawField>(
+    num_instance_columns: usize,
+    copies: impl IntoIterator<Item = (Cell, Cell)>,
+    value: impl Fn(Cell) -> CellValue<F>,
+    annotations: &HashMap<(ColumnKind, usize), String>,
+) -> Result<InferredInstance<F>, Error> {
+    let cell = |(kind, index, row): Cell| CellRef {
+        column: column_name(annotations, kind, index),
+        row,
+    };
+
+    let mut classes = UnionFind::default();
+    for (left, right) in copies {
+        classes.union(&left, &right);
+    }
+
+    let mut values: Vec<Vec<Option<F>>> = vec![vec![]; num_instance_columns];
+    let mut warnings = vec![];
+    let mut conflicts = vec![];
+    // the lowest poisoned cell of the classes without assigned cells, reported after the
+    // conflicts so that the error does not depend on the order of the classes
+    let mut poisoned_source: Option<Cell> = None;
+    for class in classes.classes() {
+        let instance_cells: Vec<_> = class
+            .iter()
+            .filter(|(kind, _, _)| *kind == ColumnKind::Instance)
+            .copied()
+            .collect();
+        if instance_cells.is_empty() {
+            continue;
+        }
+
+        // the assigned cells with a distinct value each
+        let mut sources: Vec<(Cell, F)> = vec![];
+        let mut poisoned = None;
+        for member in &class {
+            if member.0 == ColumnKind::Instance {
+                continue;
+            }
+            match value(*member) {
+                CellValue::Assigned(value) => {
+                    if !sources.iter().any(|(_, source)| *source == value) {
+                        sources.push((*member, value));
+                    }
+                }
+                CellValue::Unassigned => {}
+                CellValue::Poison(_) => {
+                    poisoned =
+                        Some(poisoned.map_or(*member, |poisoned: Cell| poisoned.min(*member)))
+                }
+            }
+        }
+
+        let value = match (sources.as_slice(), poisoned) {
+            ([(_, value)], _) => *value,
+            ([], Some(poisoned)) => {
+                if poisoned_source.map_or(true, |source| poisoned < source) {
+                    poisoned_source = Some(poisoned);
+                }
+                continue;
+            }
+            ([], None) => {
+                for instance_cell in &instance_cells {
+                    warnings.push(InstanceWarning::NoAssignedSource {
+                        cell: cell(*instance_cell),
+                        class: class
+                            .iter()
+                            .filter(|member| *member != instance_cell)
+                            .map(|member| cell(*member))
+                            .collect(),
+                    });
+                }
+                F::from(0u64)
+            }
+            _ => {
+                conflicts.push(InstanceConflict {
+                    cells: instance_cells.iter().map(|member| cell(*member)).collect(),
+                    sources: sources
+                        .iter()
+                        .map(|(source, value)| (cell(*source), ValueFormat::Auto.format(*value)))
+                        .collect(),
+                });
+                continue;
+            }
+        };
 
-        if is_left_instance || is_right_instance {
-            let (instance_column, instance_row, other_column, other_row) = if is_left_instance {
-                (left_column, left_row, right_column, right_row)
-            } else {
-                (right_column, right_row, left_column, left_row)
-            };
-
-            let other_value = match other_column.column_type() {
-                #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
-                Any::Advice(_) => prover.advice()[other_column.index()][*other_row],
-                #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
-                Any::Advice => prover.advice()[other_column.index()][*other_row],
-                Any::Fixed => prover.fixed()[other_column.index()][*other_row],
-                Any::Instance => unreachable!(),
-            };
-
-            let col = &mut instance[instance_column.index()];
-            while col.len() < *instance_row {
-                #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
-                col.push(F::ZERO);
-                #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
-                col.push(F::zero());
+        for (_, index, row) in instance_cells {
+            let column_values = &mut values[index];
+            if column_values.len() <= row {
+                column_values.resize(row + 1, None);
             }
+            column_values[row] = Some(value);
+        }
+    }
+
+    let instance = values
+        .into_iter()
+        .enumerate()
+        .map(|(column, column_values)| {
+            column_values
+                .into_iter()
+                .enumerate()
+                .map(|(row, value)| {
+                    value.unwrap_or_else(|| {
+                        warnings.push(InstanceWarning::Unreached(cell((
+                            ColumnKind::Instance,
+                            column,
+                            row,
+                        ))));
+                        F::from(0u64)
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    if !conflicts.is_empty() {
+        return Err(Error::InstanceConflicts(conflicts));
+    }
+    if let Some((kind, index, row)) = poisoned_source {
+        return Err(Error::PoisonedCell {
+            column: column_name(annotations, kind, index),
+            row,
+        });
+    }
+    Ok(InferredInstance { instance, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::example_circuit::FactorisationCircuit;
+
+    const ADVICE: ColumnKind = ColumnKind::Advice;
+    const INSTANCE: ColumnKind = ColumnKind::Instance;
+
+    fn cell(column: &str, row: usize) -> CellRef {
+        CellRef {
+            column: column.to_string(),
+            row,
+        }
+    }
+
+    /// Infers from the copies, with the values assigned to advice cells.
+    fn infer_from(
+        num_instance_columns: usize,
+        copies: &[(Cell, Cell)],
+        assigned: &[(Cell, u64)],
+    ) -> Result<InferredInstance<Fr>, Error> {
+        infer_poisoned(num_instance_columns, copies, assigned, &[])
+    }
+
+    /// Same as `infer_from`, with poisoned advice cells.
+    fn infer_poisoned(
+        num_instance_columns: usize,
+        copies: &[(Cell, Cell)],
+        assigned: &[(Cell, u64)],
+        poisoned: &[Cell],
+    ) -> Result<InferredInstance<Fr>, Error> {
+        infer(
+            num_instance_columns,
+            copies.iter().copied(),
+            |member| match assigned.iter().find(|(cell, _)| *cell == member) {
+                Some((_, value)) => CellValue::Assigned(Fr::from(*value)),
+                None if poisoned.contains(&member) => CellValue::Poison(0),
+                None => CellValue::Unassigned,
+            },
+            &HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn infers_factorisation_circuit() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: std::marker::PhantomData,
+        };
+        let instance = infer_instance(&circuit, Some(4)).unwrap();
+        assert_eq!(instance, vec![vec![Fr::from(21)]]);
+    }
 
-            col.push(parse_cell_value(other_value))
+    #[test]
+    fn follows_advice_chains() {
+        // advice[0]@0 is assigned, advice[0]@1 and advice[1]@3 only pass the value on
+        let inferred = infer_from(
+            1,
+            &[
+                ((ADVICE, 0, 0), (ADVICE, 0, 1)),
+                ((ADVICE, 0, 1), (ADVICE, 1, 3)),
+                ((ADVICE, 1, 3), (INSTANCE, 0, 2)),
+            ],
+            &[((ADVICE, 0, 0), 5)],
+        )
+        .unwrap();
+        assert_eq!(
+            inferred.instance,
+            vec![vec![Fr::from(0), Fr::from(0), Fr::from(5)]]
+        );
+        assert_eq!(
+            inferred.warnings,
+            vec![
+                InstanceWarning::Unreached(cell("instance[0]", 0)),
+                InstanceWarning::Unreached(cell("instance[0]", 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_instance_links() {
+        let inferred = infer_from(
+            2,
+            &[
+                ((INSTANCE, 0, 0), (INSTANCE, 0, 1)),
+                ((INSTANCE, 0, 1), (INSTANCE, 1, 0)),
+                ((INSTANCE, 1, 0), (ADVICE, 0, 4)),
+                // no assigned cell in this class
+                ((INSTANCE, 0, 2), (INSTANCE, 1, 1)),
+            ],
+            &[((ADVICE, 0, 4), 7)],
+        )
+        .unwrap();
+        assert_eq!(
+            inferred.instance,
+            vec![
+                vec![Fr::from(7), Fr::from(7), Fr::from(0)],
+                vec![Fr::from(7), Fr::from(0)],
+            ]
+        );
+        assert_eq!(inferred.warnings.len(), 2);
+        assert!(inferred
+            .warnings
+            .contains(&InstanceWarning::NoAssignedSource {
+                cell: cell("instance[0]", 2),
+                class: vec![cell("instance[1]", 1)],
+            }));
+    }
+
+    #[test]
+    fn reports_every_conflict() {
+        let result = infer_from(
+            1,
+            &[
+                ((ADVICE, 0, 0), (INSTANCE, 0, 0)),
+                ((INSTANCE, 0, 0), (ADVICE, 0, 1)),
+                ((ADVICE, 0, 2), (INSTANCE, 0, 1)),
+                ((INSTANCE, 0, 1), (ADVICE, 1, 2)),
+                // same value twice is not a conflict
+                ((ADVICE, 0, 3), (INSTANCE, 0, 2)),
+                ((INSTANCE, 0, 2), (ADVICE, 1, 3)),
+            ],
+            &[
+                ((ADVICE, 0, 0), 1),
+                ((ADVICE, 0, 1), 2),
+                ((ADVICE, 0, 2), 3),
+                ((ADVICE, 1, 2), 4),
+                ((ADVICE, 0, 3), 5),
+                ((ADVICE, 1, 3), 5),
+            ],
+        );
+        let conflicts = match result {
+            Err(Error::InstanceConflicts(conflicts)) => conflicts,
+            other => panic!("expected conflicts, got {:?}", other.map(|i| i.instance)),
+        };
+        assert_eq!(conflicts.len(), 2);
+        for (instance_row, sources) in [
+            (0, [(0, 0, "1"), (0, 1, "2")]),
+            (1, [(0, 2, "3"), (1, 2, "4")]),
+        ] {
+            let conflict = conflicts
+                .iter()
+                .find(|conflict| conflict.cells == vec![cell("instance[0]", instance_row)])
+                .unwrap();
+            for (column, row, value) in sources {
+                assert!(conflict
+                    .sources
+                    .contains(&(cell(&format!("advice[{}]", column), row), value.to_string())));
+            }
+            assert_eq!(conflict.sources.len(), 2);
         }
     }
 
-    instance
+    #[test]
+    fn reports_conflicts_before_poisoned_cells() {
+        let copies = [
+            ((ADVICE, 0, 9), (INSTANCE, 0, 0)),
+            ((ADVICE, 0, 0), (INSTANCE, 0, 1)),
+            ((INSTANCE, 0, 1), (ADVICE, 0, 1)),
+        ];
+        let assigned = [((ADVICE, 0, 0), 1), ((ADVICE, 0, 1), 2)];
+        let result = infer_poisoned(1, &copies, &assigned, &[(ADVICE, 0, 9)]);
+        match result {
+            Err(Error::InstanceConflicts(conflicts)) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].cells, vec![cell("instance[0]", 1)]);
+            }
+            other => panic!("expected conflicts, got {:?}", other.map(|i| i.instance)),
+        }
+
+        // without conflicts the lowest poisoned cell is reported
+        let result = infer_poisoned(
+            1,
+            &[
+                ((ADVICE, 0, 9), (INSTANCE, 0, 0)),
+                ((ADVICE, 0, 8), (INSTANCE, 0, 1)),
+                ((INSTANCE, 0, 1), (ADVICE, 0, 10)),
+            ],
+            &[],
+            &[(ADVICE, 0, 8), (ADVICE, 0, 9), (ADVICE, 0, 10)],
+        );
+        assert!(matches!(
+            result,
+            Err(Error::PoisonedCell { column, row: 8 }) if column == "advice[0]"
+        ));
+    }
 }
@@ -1,20 +1,285 @@
-use crate::halo2_proofs::plonk::{Circuit, ConstraintSystem};
+use std::fmt;
 
-use crate::RawField;
+use serde::Serialize;
+
+use crate::halo2_proofs::plonk::Circuit;
+
+use crate::{
+    assignments_printer::{cell_status, table_column, CellStatus},
+    compat::{column_annotations, column_kind, lookup_name, ColumnKind},
+    error::Error,
+    estimate_k::mock_prover,
+    expression::{column_name, lookup_degree, queried_columns, render},
+    utils::configure,
+    RawField,
+};
+
+/// Shape of the constraint system of a circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CircuitInfo {
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub gates: usize,
+    pub lookups: usize,
+    /// Columns with equality enabled.
+    pub permutation_columns: usize,
+    pub max_degree: usize,
+    /// Distinct rotations at which any column is queried, sorted.
+    pub rotations: Vec<i32>,
+}
+
+impl fmt::Display for CircuitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "advice columns: {:?}", self.advice_columns)?;
+        writeln!(f, "fixed columns: {:?}", self.fixed_columns)?;
+        writeln!(f, "instance columns: {:?}", self.instance_columns)?;
+        writeln!(f, "selectors columns: {:?}", self.selectors)?;
+        writeln!(f, "gates: {:?}", self.gates)?;
+        writeln!(f, "lookups: {:?}", self.lookups)?;
+        writeln!(f, "permutation columns: {:?}", self.permutation_columns)?;
+        writeln!(f, "max degree: {:?}", self.max_degree)?;
+        write!(f, "rotations: {:?}", self.rotations)
+    }
+}
+
+/// Collects the info for the circuit.
+pub fn collect<F: RawField, C: Circuit<F>>(
+    #[cfg(feature = "circuit-params")] circuit: &C,
+) -> CircuitInfo {
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+
+    let mut rotations: Vec<i32> = cs
+        .advice_queries()
+        .iter()
+        .map(|(_, rotation)| rotation.0)
+        .chain(cs.fixed_queries().iter().map(|(_, rotation)| rotation.0))
+        .chain(cs.instance_queries().iter().map(|(_, rotation)| rotation.0))
+        .collect();
+    rotations.sort_unstable();
+    rotations.dedup();
+
+    CircuitInfo {
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        selectors: cs.num_selectors(),
+        gates: cs.gates().len(),
+        lookups: cs.lookups().len(),
+        permutation_columns: cs.permutation().get_columns().len(),
+        max_degree: cs.degree(),
+        rotations,
+    }
+}
 
 /// Prints the info for the circuit.
 pub fn print<F: RawField, C: Circuit<F>>(#[cfg(feature = "circuit-params")] circuit: &C) {
-    #[cfg(not(feature = "circuit-params"))]
-    let mut cs = ConstraintSystem::default();
-    #[cfg(feature = "circuit-params")]
-    C::configure_with_params(&mut cs, circuit.params());
-    #[cfg(not(feature = "circuit-params"))]
-    C::configure(&mut cs);
-
-    println!("advice columns: {:?}", cs.num_advice_columns());
-    println!("fixed columns: {:?}", cs.num_fixed_columns());
-    println!("instance columns: {:?}", cs.num_instance_columns());
-    println!("selectors columns: {:?}", cs.num_selectors());
-    println!("gates: {:?}", cs.gates().len());
-    println!("lookups: {:?}", cs.lookups().len());
+    println!(
+        "{}",
+        collect::<F, C>(
+            #[cfg(feature = "circuit-params")]
+            circuit
+        )
+    );
+}
+
+/// Prints every gate with its constraints rendered as algebra.
+pub fn print_gates<F: RawField, C: Circuit<F>>(#[cfg(feature = "circuit-params")] circuit: &C) {
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let annotations = column_annotations(&cs);
+
+    for (i, gate) in cs.gates().iter().enumerate() {
+        println!("gate {}: {}", i, gate.name());
+        for (j, poly) in gate.polynomials().iter().enumerate() {
+            let name = gate.constraint_name(j);
+            if name.is_empty() {
+                println!("  constraint {} (degree {})", j, poly.degree());
+            } else {
+                println!("  constraint {}: {} (degree {})", j, name, poly.degree());
+            }
+            println!("    {}", render(poly, &annotations));
+        }
+    }
+}
+
+/// A table column of a lookup and how many of its usable rows are assigned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TableColumnInfo {
+    pub column: String,
+    pub populated_rows: usize,
+}
+
+/// A lookup argument with its expressions rendered as algebra.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LookupInfo {
+    pub name: String,
+    pub inputs: Vec<String>,
+    pub tables: Vec<String>,
+    /// Degree that this lookup adds to the constraint system.
+    pub degree: usize,
+    pub table_columns: Vec<TableColumnInfo>,
+}
+
+impl fmt::Display for LookupInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} (degree {})", self.name, self.degree)?;
+        for (input, table) in self.inputs.iter().zip(&self.tables) {
+            writeln!(f, "  {} in {}", input, table)?;
+        }
+        let columns: Vec<String> = self
+            .table_columns
+            .iter()
+            .map(|c| format!("{} ({} rows populated)", c.column, c.populated_rows))
+            .collect();
+        write!(f, "  table columns: {}", columns.join(", "))
+    }
+}
+
+/// Collects every lookup of the circuit, the populated rows of the table columns are taken
+/// from a `MockProver` run at k, which is estimated if not given.
+pub fn collect_lookups<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+) -> Result<Vec<LookupInfo>, Error> {
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let annotations = column_annotations(&cs);
+
+    // assigned cells rather than region spans, regions may overlap or leave gaps. Only the rows
+    // up to the end of the regions assigning the column count, as `assign_table` pads the
+    // table columns with a default value until the last usable row after its region.
+    let populated_rows = |kind: ColumnKind, index: usize| -> usize {
+        let column = table_column(kind, index);
+        let end = prover
+            .regions()
+            .iter()
+            .filter(|region| {
+                region
+                    .columns()
+                    .iter()
+                    .any(|column| column_kind(column) == kind && column.index() == index)
+            })
+            .filter_map(|region| region.rows())
+            .map(|(_, end)| end + 1)
+            .max()
+            .unwrap_or(0);
+        prover
+            .usable_rows()
+            .clone()
+            .take_while(|row| *row < end)
+            .filter(|row| cell_status(&prover, &column, *row).0 == CellStatus::Assigned)
+            .count()
+    };
+
+    Ok(cs
+        .lookups()
+        .iter()
+        .enumerate()
+        .map(|(i, lookup)| LookupInfo {
+            name: lookup_name(&cs, i),
+            inputs: lookup
+                .input_expressions()
+                .iter()
+                .map(|expr| render(expr, &annotations))
+                .collect(),
+            tables: lookup
+                .table_expressions()
+                .iter()
+                .map(|expr| render(expr, &annotations))
+                .collect(),
+            degree: lookup_degree(lookup.input_expressions(), lookup.table_expressions()),
+            table_columns: queried_columns(lookup.table_expressions())
+                .into_iter()
+                .map(|(kind, index)| TableColumnInfo {
+                    column: column_name(&annotations, kind, index),
+                    populated_rows: populated_rows(kind, index),
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// Prints every lookup of the circuit along with how much of its table is populated.
+pub fn print_lookups<F: RawField, C: Circuit<F>>(circuit: &C, k: Option<u32>) -> Result<(), Error> {
+    for lookup in collect_lookups(circuit, k)? {
+        println!("{}", lookup);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{example_circuit::FactorisationCircuit, test_circuits::LookupCircuit};
+
+    fn circuit() -> FactorisationCircuit<Fr> {
+        FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        }
+    }
+
+    #[test]
+    fn collects_circuit_info() {
+        #[cfg(feature = "circuit-params")]
+        let circuit = circuit();
+        let info = collect::<Fr, FactorisationCircuit<Fr>>(
+            #[cfg(feature = "circuit-params")]
+            &circuit,
+        );
+        assert_eq!(
+            info,
+            CircuitInfo {
+                advice_columns: 2,
+                fixed_columns: 0,
+                instance_columns: 1,
+                selectors: 1,
+                gates: 1,
+                lookups: 0,
+                permutation_columns: 2,
+                max_degree: 3,
+                rotations: vec![0, 1, 2],
+            }
+        );
+    }
+
+    #[test]
+    fn circuit_without_lookups() {
+        assert_eq!(collect_lookups(&circuit(), Some(4)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn counts_populated_table_rows() {
+        let circuit = LookupCircuit {
+            table_rows: 10,
+            values: vec![1, 9],
+        };
+        // 58 usable rows, the 48 rows padded after the table do not count
+        let lookups = collect_lookups(&circuit, Some(6)).unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].inputs.len(), 1);
+        assert_eq!(lookups[0].tables.len(), 1);
+        assert_eq!(
+            lookups[0].table_columns,
+            vec![TableColumnInfo {
+                column: "fixed[0]".to_string(),
+                populated_rows: 10,
+            }]
+        );
+    }
 }
@@ -0,0 +1,461 @@
+use std::{collections::HashMap, fmt::Debug, marker::PhantomData, ops::Range};
+
+use crate::halo2_proofs::{dev::MockProver, plonk::Circuit};
+use plotters::{
+    coord::{types::RangedCoordusize, Shift},
+    prelude::*,
+};
+
+use crate::{
+    derive_circuit_name,
+    error::Error,
+    estimate_k::mock_prover,
+    layout_printer::{drawing_error, encode_image, layout_column, write_image, Degree, Draw},
+    LayoutFormat, RawField,
+};
+
+const UNCHANGED_COLOR: RGBColor = RGBColor(190, 190, 190);
+const MOVED_COLOR: RGBColor = RGBColor(31, 111, 235);
+const RESIZED_COLOR: RGBColor = RGBColor(230, 140, 0);
+const ADDED_COLOR: RGBColor = RGBColor(40, 170, 60);
+const REMOVED_COLOR: RGBColor = RGBColor(210, 40, 40);
+
+/// Height of the legend below the grid, in pixels.
+const LEGEND_HEIGHT: u32 = 50;
+
+/// How a region differs between the two layouts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionChange {
+    Unchanged,
+    /// Same number of rows and columns at another place.
+    Moved,
+    /// Another number of rows or columns.
+    Resized,
+    /// Only in the second layout.
+    Added,
+    /// Only in the first layout.
+    Removed,
+}
+
+impl RegionChange {
+    fn color(&self) -> RGBColor {
+        match self {
+            RegionChange::Unchanged => UNCHANGED_COLOR,
+            RegionChange::Moved => MOVED_COLOR,
+            RegionChange::Resized => RESIZED_COLOR,
+            RegionChange::Added => ADDED_COLOR,
+            RegionChange::Removed => REMOVED_COLOR,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            RegionChange::Unchanged => "unchanged",
+            RegionChange::Moved => "moved",
+            RegionChange::Resized => "resized",
+            RegionChange::Added => "only in second",
+            RegionChange::Removed => "only in first",
+        }
+    }
+}
+
+/// Rows of a region and the layout columns it touches, instance columns first, then advice and
+/// fixed columns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionPlacement {
+    pub rows: Range<usize>,
+    pub columns: Vec<usize>,
+}
+
+/// A region matched between the two layouts. The n-th region with a name in the first layout
+/// is matched with the n-th region with that name in the second one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionDiff {
+    pub name: String,
+    pub change: RegionChange,
+    pub before: Option<RegionPlacement>,
+    pub after: Option<RegionPlacement>,
+}
+
+/// Draws the layouts of two circuits, or of one circuit at two values of k, on the same grid.
+/// Regions are colored by how they changed from the first layout to the second, moved and
+/// resized regions are filled where they are in the second layout and outlined where they were
+/// in the first one.
+///
+/// ```ignore
+/// LayoutDiff::from(&before, &after).path("diff.png").print()?;
+/// LayoutDiff::from(&circuit, &circuit).degrees(10, 12).print()?;
+/// ```
+pub struct LayoutDiff<'a, F: RawField, Before: Circuit<F>, After: Circuit<F>> {
+    _before: &'a Before,
+    _after: &'a After,
+    /// Set with `degree` or `degrees`, otherwise found out from synthesis when rendering.
+    _k_before: Option<u32>,
+    _k_after: Option<u32>,
+    _path: String,
+    _color: &'a RGBColor,
+    _dimensions: (u32, u32),
+    _title: String,
+    _marker: PhantomData<F>,
+}
+
+impl<'a, F: RawField, Before: Circuit<F> + Debug, After: Circuit<F> + Debug>
+    LayoutDiff<'a, F, Before, After>
+{
+    pub fn from(before: &'a Before, after: &'a After) -> Self {
+        let before_name = derive_circuit_name(before);
+        let after_name = derive_circuit_name(after);
+        let title = if before_name == after_name {
+            format!("{} Layout Diff", before_name)
+        } else {
+            format!("{} vs {} Layout Diff", before_name, after_name)
+        };
+        Self {
+            _before: before,
+            _after: after,
+            _k_before: None,
+            _k_after: None,
+            _path: format!("{}-layout-diff.png", after_name),
+            _color: &WHITE,
+            _dimensions: (1024, 768),
+            _title: title,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Writes the diff to the path, as SVG if the path ends with `.svg` and as PNG otherwise.
+    pub fn print(self) -> Result<(), Error> {
+        write_image(&self, &self._path, self._dimensions)
+    }
+
+    /// Renders the diff in memory and returns the encoded PNG or SVG.
+    pub fn render_to_bytes(self, format: LayoutFormat) -> Result<Vec<u8>, Error> {
+        encode_image(&self, self._dimensions, format)
+    }
+
+    /// Matches the regions of the two layouts and tells how each one changed, in the order of
+    /// the second layout followed by the regions only in the first one.
+    pub fn regions(&self) -> Result<Vec<RegionDiff>, Error> {
+        let (k_before, k_after) = self.resolve_k()?;
+        self.regions_at(k_before, k_after)
+    }
+
+    /// k of the first and the second circuit, from synthesis unless set explicitly.
+    fn resolve_k(&self) -> Result<(u32, u32), Error> {
+        Ok((
+            Degree::resolve(self._before, self._k_before)?.k,
+            Degree::resolve(self._after, self._k_after)?.k,
+        ))
+    }
+
+    fn regions_at(&self, k_before: u32, k_after: u32) -> Result<Vec<RegionDiff>, Error> {
+        let before = placements(&mock_prover(self._before, Some(k_before))?);
+        let after = placements(&mock_prover(self._after, Some(k_after))?);
+
+        let mut before_by_key: HashMap<(String, usize), RegionPlacement> = HashMap::new();
+        let mut before_keys = vec![];
+        for (key, placement) in before {
+            before_keys.push(key.clone());
+            before_by_key.insert(key, placement);
+        }
+
+        let mut diffs = vec![];
+        for ((name, occurrence), after) in after {
+            let before = before_by_key.remove(&(name.clone(), occurrence));
+            let change = match &before {
+                None => RegionChange::Added,
+                Some(before) if *before == after => RegionChange::Unchanged,
+                Some(before)
+                    if before.rows.len() == after.rows.len()
+                        && before.columns.len() == after.columns.len() =>
+                {
+                    RegionChange::Moved
+                }
+                Some(_) => RegionChange::Resized,
+            };
+            diffs.push(RegionDiff {
+                name,
+                change,
+                before,
+                after: Some(after),
+            });
+        }
+        for key in before_keys {
+            if let Some(before) = before_by_key.remove(&key) {
+                diffs.push(RegionDiff {
+                    name: key.0,
+                    change: RegionChange::Removed,
+                    before: Some(before),
+                    after: None,
+                });
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Sets the same k for both circuits.
+    pub fn degree(mut self, k: u32) -> Self {
+        self._k_before = Some(k);
+        self._k_after = Some(k);
+        self
+    }
+
+    /// Sets k for the first and the second circuit.
+    pub fn degrees(mut self, k_before: u32, k_after: u32) -> Self {
+        self._k_before = Some(k_before);
+        self._k_after = Some(k_after);
+        self
+    }
+
+    pub fn path(mut self, path: &'a str) -> Self {
+        self._path = String::from(path);
+        self
+    }
+
+    pub fn color(mut self, color: &'a RGBColor) -> Self {
+        self._color = color;
+        self
+    }
+
+    pub fn dimensions(mut self, dimensions: (u32, u32)) -> Self {
+        self._dimensions = dimensions;
+        self
+    }
+
+    pub fn title(mut self, title: &'a str) -> Self {
+        self._title = String::from(title);
+        self
+    }
+}
+
+impl<F: RawField, Before: Circuit<F> + Debug, After: Circuit<F> + Debug> Draw
+    for LayoutDiff<'_, F, Before, After>
+{
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Error> {
+        let (k_before, k_after) = self.resolve_k()?;
+        let diffs = self.regions_at(k_before, k_after)?;
+        let rows = 1 << std::cmp::max(k_before, k_after);
+        let columns = diffs
+            .iter()
+            .flat_map(|diff| diff.before.iter().chain(diff.after.iter()))
+            .flat_map(|placement| placement.columns.iter())
+            .max()
+            .map_or(1, |column| column + 1);
+
+        root.fill(self._color).map_err(drawing_error)?;
+        let title = if k_before == k_after {
+            format!("{} (k={})", self._title, k_after)
+        } else {
+            format!("{} (k={} vs k={})", self._title, k_before, k_after)
+        };
+        let root = root
+            .titled(title.as_str(), ("sans-serif", 60))
+            .map_err(drawing_error)?;
+        let (_, height) = root.dim_in_pixel();
+        let (grid, legend) = root.split_vertically(height.saturating_sub(LEGEND_HEIGHT));
+
+        // row 0 at the top, like in `CircuitLayout`
+        let grid = grid.apply_coord_spec(Cartesian2d::<RangedCoordusize, RangedCoordusize>::new(
+            0..columns,
+            0..rows,
+            grid.get_pixel_range(),
+        ));
+        grid.draw(&Rectangle::new([(0, 0), (columns, rows)], BLACK))
+            .map_err(drawing_error)?;
+
+        for diff in &diffs {
+            let color = diff.change.color();
+            match diff.change {
+                RegionChange::Removed => {
+                    draw_placement(&grid, diff.before.as_ref(), color.mix(0.5).filled())?
+                }
+                RegionChange::Moved | RegionChange::Resized => {
+                    draw_placement(&grid, diff.before.as_ref(), color.stroke_width(2))?;
+                    draw_placement(&grid, diff.after.as_ref(), color.mix(0.5).filled())?;
+                }
+                RegionChange::Unchanged | RegionChange::Added => {
+                    draw_placement(&grid, diff.after.as_ref(), color.mix(0.5).filled())?
+                }
+            }
+        }
+
+        let changes = [
+            RegionChange::Unchanged,
+            RegionChange::Moved,
+            RegionChange::Resized,
+            RegionChange::Added,
+            RegionChange::Removed,
+        ];
+        let (width, _) = legend.dim_in_pixel();
+        let entry_width = width as i32 / changes.len() as i32;
+        for (i, change) in changes.iter().enumerate() {
+            let x = i as i32 * entry_width + 10;
+            let y = LEGEND_HEIGHT as i32 / 2;
+            legend
+                .draw(&Rectangle::new(
+                    [(x, y - 8), (x + 16, y + 8)],
+                    change.color().mix(0.5).filled(),
+                ))
+                .map_err(drawing_error)?;
+            legend
+                .draw(&Text::new(
+                    change.label(),
+                    (x + 24, y - 8),
+                    ("sans-serif", 16),
+                ))
+                .map_err(drawing_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Placement of every region that has rows, keyed by name and occurrence of the name.
+fn placements<F: RawField>(prover: &MockProver<F>) -> Vec<((String, usize), RegionPlacement)> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    let mut placements = vec![];
+    for region in prover.regions() {
+        let (start, end) = match region.rows() {
+            Some(rows) => rows,
+            None => continue,
+        };
+        let mut columns: Vec<usize> = region
+            .columns()
+            .iter()
+            .map(|column| layout_column(prover.cs(), column))
+            .collect();
+        columns.sort_unstable();
+        let occurrence = occurrences.entry(region.name().to_string()).or_default();
+        placements.push((
+            (region.name().to_string(), *occurrence),
+            RegionPlacement {
+                rows: start..end + 1,
+                columns,
+            },
+        ));
+        *occurrence += 1;
+    }
+    placements
+}
+
+type Grid<DB> = DrawingArea<DB, Cartesian2d<RangedCoordusize, RangedCoordusize>>;
+
+fn draw_placement<DB: DrawingBackend>(
+    grid: &Grid<DB>,
+    placement: Option<&RegionPlacement>,
+    style: ShapeStyle,
+) -> Result<(), Error> {
+    let placement = match placement {
+        Some(placement) => placement,
+        None => return Ok(()),
+    };
+    for column in &placement.columns {
+        grid.draw(&Rectangle::new(
+            [
+                (*column, placement.rows.start),
+                (*column + 1, placement.rows.end),
+            ],
+            style,
+        ))
+        .map_err(drawing_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use crate::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{example_circuit::FactorisationCircuit, test_circuits::RegionsCircuit};
+
+    fn changes(diffs: &[RegionDiff]) -> Vec<(&str, RegionChange)> {
+        diffs
+            .iter()
+            .map(|diff| (diff.name.as_str(), diff.change))
+            .collect()
+    }
+
+    #[test]
+    fn same_circuit_is_unchanged() {
+        let circuit = FactorisationCircuit {
+            a: Fr::from(3),
+            b: Fr::from(7),
+            _marker: PhantomData,
+        };
+        let diffs = LayoutDiff::from(&circuit, &circuit)
+            .degrees(4, 4)
+            .regions()
+            .unwrap();
+        assert_eq!(
+            changes(&diffs),
+            vec![("region main", RegionChange::Unchanged)]
+        );
+        assert_eq!(diffs[0].before, diffs[0].after);
+
+        // regions stay where they are at a larger k
+        let circuit = RegionsCircuit {
+            regions: vec![("a", 0, 2), ("a", 0, 1), ("b", 1, 3)],
+        };
+        let diffs = LayoutDiff::from(&circuit, &circuit)
+            .degrees(4, 5)
+            .regions()
+            .unwrap();
+        assert_eq!(
+            changes(&diffs),
+            vec![
+                ("a", RegionChange::Unchanged),
+                ("a", RegionChange::Unchanged),
+                ("b", RegionChange::Unchanged),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_changed_regions() {
+        let before = RegionsCircuit {
+            regions: vec![("a", 0, 2), ("b", 0, 2), ("c", 1, 1), ("d", 1, 1)],
+        };
+        // `b` grows, `x` takes the place of `c` which moves down, `d` goes away
+        let after = RegionsCircuit {
+            regions: vec![("a", 0, 2), ("b", 0, 3), ("x", 1, 1), ("c", 1, 1)],
+        };
+        let diffs = LayoutDiff::from(&before, &after)
+            .degree(4)
+            .regions()
+            .unwrap();
+        assert_eq!(
+            changes(&diffs),
+            vec![
+                ("a", RegionChange::Unchanged),
+                ("b", RegionChange::Resized),
+                ("x", RegionChange::Added),
+                ("c", RegionChange::Moved),
+                ("d", RegionChange::Removed),
+            ]
+        );
+
+        let rows = |placement: &Option<RegionPlacement>| placement.as_ref().map(|p| p.rows.clone());
+        assert_eq!(
+            (rows(&diffs[1].before), rows(&diffs[1].after)),
+            (Some(2..4), Some(2..5))
+        );
+        assert_eq!(
+            (rows(&diffs[2].before), rows(&diffs[2].after)),
+            (None, Some(0..1))
+        );
+        assert_eq!(
+            (rows(&diffs[3].before), rows(&diffs[3].after)),
+            (Some(0..1), Some(1..2))
+        );
+        assert_eq!(
+            diffs[3].before.as_ref().unwrap().columns,
+            diffs[3].after.as_ref().unwrap().columns
+        );
+        assert_eq!(
+            (rows(&diffs[4].before), rows(&diffs[4].after)),
+            (Some(1..2), None)
+        );
+    }
+}
@@ -1,17 +1,56 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{fmt::Debug, marker::PhantomData, ops::Range, path::Path};
 
-use crate::halo2_proofs::{arithmetic::Field, dev::CircuitLayout, plonk::Circuit};
-use plotters::prelude::*;
+use crate::halo2_proofs::{
+    dev::CircuitLayout,
+    plonk::{self, Any, Circuit, ConstraintSystem},
+};
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use plotters::{coord::Shift, drawing::DrawingAreaErrorKind, prelude::*};
 
-use crate::{derive_circuit_name, utils::derive_k};
+use crate::{
+    compat::{column_kind, ColumnKind},
+    derive_circuit_name,
+    error::Error,
+    estimate_k::mock_prover,
+    row_usage, RawField,
+};
 
-pub struct LayoutPrinter<'a, F: Field, ConcreteCircuit: Circuit<F>> {
+/// Width of the row numbers in front of each line of text layouts.
+const TEXT_ROW_LABEL_WIDTH: usize = 8;
+/// ANSI colors given to region names in text layouts.
+const TEXT_COLORS: [u8; 12] = [39, 208, 70, 170, 220, 45, 203, 141, 118, 33, 214, 99];
+
+/// Image format of a rendered layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutFormat {
+    Png,
+    Svg,
+}
+
+impl LayoutFormat {
+    /// Picks the format from the extension of the path, PNG unless it ends with `.svg`.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("svg") => LayoutFormat::Svg,
+            _ => LayoutFormat::Png,
+        }
+    }
+}
+
+pub struct LayoutPrinter<'a, F: RawField, ConcreteCircuit: Circuit<F>> {
     // main params
-    _k: u32,
+    /// Set with `degree`, otherwise found out from synthesis when rendering.
+    _k: Option<u32>,
     _circuit: &'a ConcreteCircuit,
     // configurable params
-    _layout: CircuitLayout,
+    /// Applied to a fresh `CircuitLayout` for every image, as rendering consumes the layout.
+    _layout_fns: Vec<Box<dyn Fn(CircuitLayout) -> CircuitLayout + 'a>>,
     // _root: DrawingArea<BitMapBackend<'a>, plotters::coord::Shift>,
+    // viewport
+    _rows: Option<Range<usize>>,
+    _columns: Option<Range<usize>>,
+    _region: Option<String>,
+    _tile_rows: Option<usize>,
     // some configs
     _path: String,
     _color: &'a RGBColor,
@@ -21,17 +60,57 @@ pub struct LayoutPrinter<'a, F: Field, ConcreteCircuit: Circuit<F>> {
     _marker: PhantomData<F>,
 }
 
-impl<'a, F: Field, ConcreteCircuit: Circuit<F> + Debug> LayoutPrinter<'a, F, ConcreteCircuit> {
+/// k of a layout and why it has this value.
+pub(crate) struct Degree {
+    pub(crate) k: u32,
+    /// Shown in the title, `None` when k was set explicitly.
+    reason: Option<String>,
+}
+
+impl Degree {
+    /// The given k, or the k of the rows used by synthesis, as `minimum_rows` alone is too
+    /// small for most circuits. Fails when synthesis fails.
+    pub(crate) fn resolve<F: RawField, C: Circuit<F>>(
+        circuit: &C,
+        k: Option<u32>,
+    ) -> Result<Self, Error> {
+        if let Some(k) = k {
+            return Ok(Degree { k, reason: None });
+        }
+        let report = row_usage(circuit)?;
+        Ok(Degree {
+            k: report.k(),
+            reason: Some(format!("set by {}", report.bottleneck())),
+        })
+    }
+
+    fn title(&self, title: &str) -> String {
+        match &self.reason {
+            Some(reason) => format!("{} (k={}, {})", title, self.k, reason),
+            None => format!("{} (k={})", title, self.k),
+        }
+    }
+}
+
+/// Rows and columns drawn in one image, everything when not set.
+#[derive(Clone, Debug, Default)]
+struct View {
+    rows: Option<Range<usize>>,
+    columns: Option<Range<usize>>,
+}
+
+impl<'a, F: RawField, ConcreteCircuit: Circuit<F> + Debug> LayoutPrinter<'a, F, ConcreteCircuit> {
     pub fn from(circuit: &'a ConcreteCircuit) -> Self {
         let circuit_name = derive_circuit_name(circuit);
         Self {
-            _k: derive_k::<F, ConcreteCircuit>(),
+            _k: None,
             _circuit: circuit,
-            _layout: CircuitLayout::default()
-                .mark_equality_cells(true)
-                .show_equality_constraints(true)
-                .show_labels(true),
+            _layout_fns: vec![],
             // _root: root,
+            _rows: None,
+            _columns: None,
+            _region: None,
+            _tile_rows: None,
             _path: format!("{}-layout.png", circuit_name),
             _color: &WHITE,
             _dimensions: (1024, 768),
@@ -40,21 +119,283 @@ impl<'a, F: Field, ConcreteCircuit: Circuit<F> + Debug> LayoutPrinter<'a, F, Con
         }
     }
 
-    pub fn print(self) {
-        let root = BitMapBackend::new(self._path.as_str(), self._dimensions).into_drawing_area();
-        root.fill(&WHITE).unwrap();
+    /// Writes the layout to the path, as SVG if the path ends with `.svg` and as PNG otherwise.
+    /// With `tile_rows` every tile is written next to the path with its number appended to the
+    /// file name, e.g. `layout-0.png`, `layout-1.png`.
+    pub fn print(self) -> Result<(), Error> {
+        let degree = Degree::resolve(self._circuit, self._k)?;
+        let views = self.views(&degree)?;
+        let tiled = views.len() > 1;
+        for (i, view) in views.iter().enumerate() {
+            let path = if tiled {
+                tile_path(&self._path, i)
+            } else {
+                self._path.clone()
+            };
+            write_image(&self.image(view, &degree), &path, self._dimensions)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the layout in memory and returns the encoded PNG or SVG. Tiling is ignored, see
+    /// `render_tiles`.
+    pub fn render_to_bytes(self, format: LayoutFormat) -> Result<Vec<u8>, Error> {
+        let degree = Degree::resolve(self._circuit, self._k)?;
+        let view = self.viewport(degree.k)?;
+        encode_image(&self.image(&view, &degree), self._dimensions, format)
+    }
+
+    /// Renders every tile in memory, a single image if `tile_rows` is not set.
+    pub fn render_tiles(self, format: LayoutFormat) -> Result<Vec<Vec<u8>>, Error> {
+        let degree = Degree::resolve(self._circuit, self._k)?;
+        self.views(&degree)?
+            .iter()
+            .map(|view| encode_image(&self.image(view, &degree), self._dimensions, format))
+            .collect()
+    }
+
+    fn image<'p>(
+        &'p self,
+        view: &'p View,
+        degree: &'p Degree,
+    ) -> Image<'p, 'a, F, ConcreteCircuit> {
+        Image {
+            printer: self,
+            view,
+            degree,
+        }
+    }
+
+    fn draw<DB: DrawingBackend>(
+        &self,
+        view: &View,
+        degree: &Degree,
+        root: &DrawingArea<DB, Shift>,
+    ) -> Result<(), Error> {
+        root.fill(self._color).map_err(drawing_error)?;
+        let mut title = degree.title(&self._title);
+        if let Some(rows) = &view.rows {
+            title = format!("{} rows {}..{}", title, rows.start, rows.end);
+        }
         let root = root
-            .titled(
-                format!("{} (k={})", self._title, self._k).as_str(),
-                ("sans-serif", 60),
-            )
-            .unwrap();
+            .titled(title.as_str(), ("sans-serif", 60))
+            .map_err(drawing_error)?;
+
+        let mut layout = self._layout_fns.iter().fold(
+            CircuitLayout::default()
+                .mark_equality_cells(true)
+                .show_equality_constraints(true)
+                .show_labels(true),
+            |layout, layout_fn| layout_fn(layout),
+        );
+        if let Some(rows) = &view.rows {
+            layout = layout.view_height(rows.clone());
+        }
+        if let Some(columns) = &view.columns {
+            layout = layout.view_width(columns.clone());
+        }
+        layout
+            .render(degree.k, self._circuit, &root)
+            .map_err(drawing_error)
+    }
+
+    /// The rows and columns to draw, from the region and the explicit ranges.
+    fn viewport(&self, k: u32) -> Result<View, Error> {
+        let mut view = match &self._region {
+            Some(name) => self.region_view(name, k)?,
+            None => View::default(),
+        };
+        if let Some(rows) = &self._rows {
+            view.rows = Some(rows.clone());
+        }
+        if let Some(columns) = &self._columns {
+            view.columns = Some(columns.clone());
+        }
+        Ok(view)
+    }
+
+    /// The viewport split into tiles of `tile_rows` rows.
+    fn views(&self, degree: &Degree) -> Result<Vec<View>, Error> {
+        let view = self.viewport(degree.k)?;
+        let tile_rows = match self._tile_rows {
+            Some(tile_rows) => std::cmp::max(tile_rows, 1),
+            None => return Ok(vec![view]),
+        };
+        let rows = view.rows.clone().unwrap_or(0..1 << degree.k);
+        Ok(rows
+            .clone()
+            .step_by(tile_rows)
+            .map(|start| View {
+                rows: Some(start..std::cmp::min(start + tile_rows, rows.end)),
+                columns: view.columns.clone(),
+            })
+            .collect())
+    }
+
+    /// Rows of the first region with the name, and the columns it touches.
+    fn region_view(&self, name: &str, k: u32) -> Result<View, Error> {
+        let prover = mock_prover(self._circuit, Some(k))?;
+        let region = prover
+            .regions()
+            .iter()
+            .find(|region| *region.name() == *name)
+            .ok_or_else(|| Error::RegionNotFound(name.to_string()))?;
+        let (start, end) = region
+            .rows()
+            .ok_or_else(|| Error::RegionNotFound(name.to_string()))?;
+
+        let columns: Vec<usize> = region
+            .columns()
+            .iter()
+            .map(|column| layout_column(prover.cs(), column))
+            .collect();
+        Ok(View {
+            rows: Some(start..end + 1),
+            columns: match (columns.iter().min(), columns.iter().max()) {
+                (Some(first), Some(last)) => Some(*first..*last + 1),
+                _ => None,
+            },
+        })
+    }
+
+    /// Prints the layout as text scaled to the size of the terminal, see `render_text`.
+    pub fn print_text(self) -> Result<(), Error> {
+        let (width, height) = terminal_size();
+        println!("{}", self.render_text(width, height)?);
+        Ok(())
+    }
+
+    /// Renders the layout as text of at most `width` by `height` characters, with regions
+    /// drawn as blocks colored after their name, copy-constrained cells marked with `◆` and
+    /// unusable rows shaded with `░`. A legend of the region names follows the drawing. Each
+    /// character stands for a block of rows and columns when the layout does not fit.
+    pub fn render_text(self, width: usize, height: usize) -> Result<String, Error> {
+        let degree = Degree::resolve(self._circuit, self._k)?;
+        let view = self.viewport(degree.k)?;
+        let prover = mock_prover(self._circuit, Some(degree.k))?;
+        let cs = prover.cs();
+        let num_columns =
+            cs.num_instance_columns() + cs.num_advice_columns() + cs.num_fixed_columns();
+        let rows = view.rows.unwrap_or(0..1 << degree.k);
+        let columns = view.columns.unwrap_or(0..num_columns);
 
-        self._layout.render(self._k, self._circuit, &root).unwrap();
+        let mut names: Vec<String> = vec![];
+        for region in prover.regions() {
+            if region.rows().is_some() && !names.iter().any(|name| name == region.name()) {
+                names.push(region.name().to_string());
+            }
+        }
+
+        // leave room for the title and the legend
+        let grid_height = std::cmp::max(height.saturating_sub(names.len() + 4), 4);
+        let grid_width = std::cmp::max(width.saturating_sub(TEXT_ROW_LABEL_WIDTH + 1), 4);
+        // characters covered by a range of rows or columns, at least one
+        let scale = |range: &Range<usize>, start: usize, end: usize, size: usize| {
+            let len = std::cmp::max(range.end - range.start, 1);
+            let first = (start.clamp(range.start, range.end) - range.start) * size / len;
+            let last = (end.clamp(range.start, range.end) - range.start) * size / len;
+            first..std::cmp::max(last, first + 1).min(size)
+        };
+        let rows_len = std::cmp::max(rows.end - rows.start, 1);
+        let grid_height = std::cmp::min(grid_height, rows_len);
+        let grid_width = std::cmp::min(grid_width, std::cmp::max(columns.end - columns.start, 1));
+
+        let mut grid: Vec<Vec<Option<usize>>> = vec![vec![None; grid_width]; grid_height];
+        for region in prover.regions() {
+            let (start, end) = match region.rows() {
+                Some(region_rows) => region_rows,
+                None => continue,
+            };
+            if end < rows.start || start >= rows.end {
+                continue;
+            }
+            let name = names.iter().position(|name| name == region.name());
+            for column in region.columns() {
+                let column = layout_column(cs, column);
+                if !columns.contains(&column) {
+                    continue;
+                }
+                for y in scale(&rows, start, end + 1, grid_height) {
+                    for x in scale(&columns, column, column + 1, grid_width) {
+                        grid[y][x] = name;
+                    }
+                }
+            }
+        }
+
+        let mut marked = vec![vec![false; grid_width]; grid_height];
+        for (left_column, left_row, right_column, right_row) in
+            prover.permutation().copy_constraints()
+        {
+            for (column, row) in [(left_column, *left_row), (right_column, *right_row)] {
+                let column = layout_column(cs, column);
+                if columns.contains(&column) && rows.contains(&row) {
+                    let y = scale(&rows, row, row + 1, grid_height).start;
+                    let x = scale(&columns, column, column + 1, grid_width).start;
+                    marked[y][x] = true;
+                }
+            }
+        }
+
+        let usable_end = prover.usable_rows().end;
+        let mut text = format!("{}\n", degree.title(&self._title));
+        for (y, line) in grid.iter().enumerate() {
+            let first_row = rows.start + y * rows_len / grid_height;
+            text += &format!("{:>width$} ", first_row, width = TEXT_ROW_LABEL_WIDTH);
+            for (x, name) in line.iter().enumerate() {
+                let symbol = if marked[y][x] {
+                    '◆'
+                } else if name.is_some() {
+                    '█'
+                } else if first_row >= usable_end {
+                    '░'
+                } else {
+                    '·'
+                };
+                match name {
+                    Some(name) => text += &colored(symbol, *name),
+                    None => text.push(symbol),
+                }
+            }
+            text.push('\n');
+        }
+
+        for (i, name) in names.iter().enumerate() {
+            text += &format!("{} {}\n", colored('█', i), name);
+        }
+        text += "◆ copy constrained cell\n";
+        text += "░ unusable rows";
+        Ok(text)
     }
 
     pub fn degree(mut self, k: u32) -> Self {
-        self._k = k;
+        self._k = Some(k);
+        self
+    }
+
+    /// Draws only these rows.
+    pub fn rows(mut self, rows: Range<usize>) -> Self {
+        self._rows = Some(rows);
+        self
+    }
+
+    /// Draws only these columns. Columns are numbered like in the layout, instance columns
+    /// first, then advice and fixed columns.
+    pub fn columns(mut self, columns: Range<usize>) -> Self {
+        self._columns = Some(columns);
+        self
+    }
+
+    /// Draws only the rows and columns of the first region with this name. Ranges set with
+    /// `rows` and `columns` take precedence.
+    pub fn region(mut self, name: &str) -> Self {
+        self._region = Some(name.to_string());
+        self
+    }
+
+    /// Splits the rows into several images of this many rows each.
+    pub fn tile_rows(mut self, rows: usize) -> Self {
+        self._tile_rows = Some(rows);
         self
     }
 
@@ -80,9 +421,9 @@ impl<'a, F: Field, ConcreteCircuit: Circuit<F> + Debug> LayoutPrinter<'a, F, Con
 
     pub fn layout_config<CL>(mut self, layout_fn: CL) -> Self
     where
-        CL: Fn(CircuitLayout) -> CircuitLayout,
+        CL: Fn(CircuitLayout) -> CircuitLayout + 'a,
     {
-        self._layout = layout_fn(self._layout);
+        self._layout_fns.push(Box::new(layout_fn));
         self
     }
 
@@ -96,3 +437,133 @@ impl<'a, F: Field, ConcreteCircuit: Circuit<F> + Debug> LayoutPrinter<'a, F, Con
     //     self
     // }
 }
+
+/// One image of a `LayoutPrinter`.
+struct Image<'p, 'a, F: RawField, ConcreteCircuit: Circuit<F>> {
+    printer: &'p LayoutPrinter<'a, F, ConcreteCircuit>,
+    view: &'p View,
+    degree: &'p Degree,
+}
+
+impl<F: RawField, ConcreteCircuit: Circuit<F> + Debug> Draw for Image<'_, '_, F, ConcreteCircuit> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Error> {
+        self.printer.draw(self.view, self.degree, root)
+    }
+}
+
+/// Something drawn into an image by `write_image` and `encode_image`.
+pub(crate) trait Draw {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<(), Error>;
+}
+
+/// Draws the image into the file, as SVG if the path ends with `.svg` and as PNG otherwise.
+pub(crate) fn write_image<D: Draw>(
+    image: &D,
+    path: &str,
+    dimensions: (u32, u32),
+) -> Result<(), Error> {
+    match LayoutFormat::from_path(path) {
+        LayoutFormat::Png => {
+            let root = BitMapBackend::new(path, dimensions).into_drawing_area();
+            image.draw(&root)?;
+            root.present().map_err(drawing_error)
+        }
+        LayoutFormat::Svg => {
+            let root = SVGBackend::new(path, dimensions).into_drawing_area();
+            image.draw(&root)?;
+            root.present().map_err(drawing_error)
+        }
+    }
+}
+
+/// Draws the image in memory and returns the encoded PNG or SVG.
+pub(crate) fn encode_image<D: Draw>(
+    image: &D,
+    (width, height): (u32, u32),
+    format: LayoutFormat,
+) -> Result<Vec<u8>, Error> {
+    match format {
+        LayoutFormat::Png => {
+            let mut buffer = vec![0; width as usize * height as usize * 3];
+            {
+                let root =
+                    BitMapBackend::with_buffer(&mut buffer, (width, height)).into_drawing_area();
+                image.draw(&root)?;
+                root.present().map_err(drawing_error)?;
+            }
+            let mut png = vec![];
+            PngEncoder::new(&mut png).write_image(&buffer, width, height, ColorType::Rgb8)?;
+            Ok(png)
+        }
+        LayoutFormat::Svg => {
+            let mut svg = String::new();
+            {
+                let root = SVGBackend::with_string(&mut svg, (width, height)).into_drawing_area();
+                image.draw(&root)?;
+                root.present().map_err(drawing_error)?;
+            }
+            Ok(svg.into_bytes())
+        }
+    }
+}
+
+/// Position of a column in the layout, instance columns come first, then advice and fixed
+/// columns.
+pub(crate) fn layout_column<F: RawField>(
+    cs: &ConstraintSystem<F>,
+    column: &plonk::Column<Any>,
+) -> usize {
+    column.index()
+        + match column_kind(column) {
+            ColumnKind::Instance => 0,
+            ColumnKind::Advice => cs.num_instance_columns(),
+            ColumnKind::Fixed => cs.num_instance_columns() + cs.num_advice_columns(),
+        }
+}
+
+/// Wraps the character in the 256 color ANSI escape of the region name.
+fn colored(symbol: char, name: usize) -> String {
+    format!(
+        "\x1b[38;5;{}m{}\x1b[0m",
+        TEXT_COLORS[name % TEXT_COLORS.len()],
+        symbol
+    )
+}
+
+/// Size of the terminal in characters, from the environment when it cannot be queried, e.g.
+/// when the output is not a terminal or without the `tui` feature.
+fn terminal_size() -> (usize, usize) {
+    #[cfg(feature = "tui")]
+    if let Ok((width, height)) = crossterm::terminal::size() {
+        return (width as usize, height as usize);
+    }
+    let from_env = |name: &str, default: usize| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    (from_env("COLUMNS", 100), from_env("LINES", 40))
+}
+
+/// Appends the tile number to the file name, before the extension.
+fn tile_path(path: &str, tile: usize) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, tile, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, tile),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub(crate) fn drawing_error<E: std::error::Error + Send + Sync>(
+    err: DrawingAreaErrorKind<E>,
+) -> Error {
+    Error::DrawingError(err.to_string())
+}
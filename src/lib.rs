@@ -6,18 +6,41 @@ pub mod error;
 
 mod utils;
 
+mod compat;
+
+mod expression;
+
+mod union_find;
+
+#[cfg(test)]
+mod test_circuits;
+
+pub mod value_format;
+pub use value_format::ValueFormat;
+
 pub use utils::*;
 
-#[cfg(feature = "latest-halo2")]
 pub mod assignments_printer;
-#[cfg(feature = "latest-halo2")]
 pub use assignments_printer as assignments;
+#[cfg(feature = "tui")]
+pub mod assignments_viewer;
+mod column_selector;
+
+pub mod cost;
+
+pub mod gate_evaluator;
 
 pub mod info_printer;
 pub use info_printer as info;
 
+pub mod permutation_printer;
+pub use permutation_printer as permutation;
+
+pub mod html_layout;
 mod layout_printer;
-pub use layout_printer::LayoutPrinter;
+pub use layout_printer::{LayoutFormat, LayoutPrinter};
+mod layout_diff;
+pub use layout_diff::{LayoutDiff, RegionChange, RegionDiff, RegionPlacement};
 
 #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
 pub mod real_prover;
@@ -27,10 +50,17 @@ pub use real_prover::RealProver;
 // #[cfg(feature = "latest-halo2")]
 mod estimate_k;
 // #[cfg(feature = "latest-halo2")]
-pub use estimate_k::estimate_k;
+pub use estimate_k::{
+    estimate_k, estimate_k_ext, estimate_k_ext_with_max, estimate_k_with_max, estimate_k_with_mode,
+    row_usage, row_usage_ext, row_usage_ext_with_max, row_usage_with_max, EstimateMode, RowSpan,
+    RowUsageReport, RowUsageSource, DEFAULT_MAX_K,
+};
 
 mod infer_instance;
-pub use infer_instance::infer_instance;
+pub use infer_instance::{
+    infer_instance, infer_instance_with_warnings, InferredInstance, InstanceConflict,
+    InstanceWarning,
+};
 
 use halo2_proofs::plonk::Circuit;
 
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::halo2_proofs::plonk::Circuit;
+
+use crate::{
+    compat::{column_annotations, column_kind, ColumnKind},
+    error::Error,
+    estimate_k::mock_prover,
+    expression::column_name,
+    union_find::UnionFind,
+    utils::configure,
+    RawField,
+};
+
+/// Number of equivalence classes kept in `PermutationReport::largest_classes`.
+const LARGEST_CLASSES: usize = 5;
+
+/// A cell of the plonkish table.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct CellRef {
+    pub column: String,
+    pub row: usize,
+}
+
+impl fmt::Display for CellRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.column, self.row)
+    }
+}
+
+/// Summary of the permutation argument of a circuit.
+#[derive(Clone, Debug, Serialize)]
+pub struct PermutationReport {
+    /// Columns with equality enabled.
+    pub equality_columns: Vec<String>,
+    pub copy_constraints: usize,
+    /// Number of sets of cells joined together by copy constraints.
+    pub equivalence_classes: usize,
+    pub largest_classes: Vec<Vec<CellRef>>,
+    /// Columns with equality enabled which are never part of a copy constraint. Each of them
+    /// still costs a column in the permutation argument.
+    pub unused_columns: Vec<String>,
+}
+
+impl fmt::Display for PermutationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "equality columns: {} ({})",
+            self.equality_columns.len(),
+            self.equality_columns.join(", ")
+        )?;
+        writeln!(f, "copy constraints: {}", self.copy_constraints)?;
+        writeln!(f, "equivalence classes: {}", self.equivalence_classes)?;
+        writeln!(f, "largest classes:")?;
+        for class in &self.largest_classes {
+            let cells: Vec<String> = class.iter().map(|cell| cell.to_string()).collect();
+            writeln!(f, "  {} cells: {}", class.len(), cells.join(", "))?;
+        }
+        if self.unused_columns.is_empty() {
+            write!(f, "unused equality columns: none")
+        } else {
+            write!(
+                f,
+                "unused equality columns: {}",
+                self.unused_columns.join(", ")
+            )
+        }
+    }
+}
+
+/// Collects the permutation report from a `MockProver` run at k, which is estimated if not given.
+pub fn collect<F: RawField, C: Circuit<F>>(
+    circuit: &C,
+    k: Option<u32>,
+) -> Result<PermutationReport, Error> {
+    let prover = mock_prover(circuit, k)?;
+    let cs = configure::<F, C>(
+        #[cfg(feature = "circuit-params")]
+        circuit,
+    );
+    let annotations = column_annotations(&cs);
+
+    let equality_columns: Vec<(ColumnKind, usize)> = cs
+        .permutation()
+        .get_columns()
+        .iter()
+        .map(|column| (column_kind(column), column.index()))
+        .collect();
+
+    let copy_constraints = prover.permutation().copy_constraints();
+    let mut classes = UnionFind::default();
+    let mut copied_columns = vec![];
+    for (left_column, left_row, right_column, right_row) in copy_constraints {
+        let left = (column_kind(left_column), left_column.index());
+        let right = (column_kind(right_column), right_column.index());
+        classes.union(&(left, *left_row), &(right, *right_row));
+        for column in [left, right] {
+            if !copied_columns.contains(&column) {
+                copied_columns.push(column);
+            }
+        }
+    }
+    let classes = classes.classes();
+
+    let name = |(kind, index): (ColumnKind, usize)| column_name(&annotations, kind, index);
+    Ok(PermutationReport {
+        equality_columns: equality_columns.iter().map(|c| name(*c)).collect(),
+        copy_constraints: copy_constraints.len(),
+        equivalence_classes: classes.len(),
+        largest_classes: classes
+            .iter()
+            .take(LARGEST_CLASSES)
+            .map(|class| {
+                class
+                    .iter()
+                    .map(|(column, row)| CellRef {
+                        column: name(*column),
+                        row: *row,
+                    })
+                    .collect()
+            })
+            .collect(),
+        unused_columns: equality_columns
+            .iter()
+            .filter(|column| !copied_columns.contains(column))
+            .map(|c| name(*c))
+            .collect(),
+    })
+}
+
+/// Prints the permutation report.
+pub fn print<F: RawField, C: Circuit<F>>(circuit: &C, k: Option<u32>) -> Result<(), Error> {
+    println!("{}", collect(circuit, k)?);
+    Ok(())
+}
@@ -0,0 +1,260 @@
+//! Small circuits exercising one feature each, for the unit tests.
+
+use crate::halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::bn256::Fr,
+    plonk::{self, Advice, Circuit, Column, ConstraintSystem, Selector, TableColumn},
+    poly::Rotation,
+};
+
+/// Assigns `rows` cells of an advice column in a single region.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct RowsCircuit {
+    pub rows: usize,
+}
+
+impl Circuit<Fr> for RowsCircuit {
+    type Config = Column<Advice>;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        meta.advice_column()
+    }
+
+    fn synthesize(
+        &self,
+        advice: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                for row in 0..self.rows {
+                    region.assign_advice(
+                        || "row",
+                        advice,
+                        row,
+                        || Value::known(Fr::from(row as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct LookupConfig {
+    selector: Selector,
+    value: Column<Advice>,
+    table: TableColumn,
+}
+
+/// Looks up `values` in a table holding `0..table_rows`. The values are assigned in region
+/// "values", the selector of the lookup is enabled on the same rows by region "enable lookup".
+#[derive(Clone, Default, Debug)]
+pub(crate) struct LookupCircuit {
+    pub table_rows: usize,
+    pub values: Vec<u64>,
+}
+
+impl Circuit<Fr> for LookupCircuit {
+    type Config = LookupConfig;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let selector = meta.complex_selector();
+        let value = meta.advice_column();
+        let table = meta.lookup_table_column();
+
+        let table_map = |meta: &mut plonk::VirtualCells<Fr>| {
+            let selector = meta.query_selector(selector);
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![(selector * value, table)]
+        };
+        #[cfg(any(feature = "v030-halo2", feature = "latest-halo2"))]
+        meta.lookup("range", table_map);
+        #[cfg(not(any(feature = "v030-halo2", feature = "latest-halo2")))]
+        meta.lookup(table_map);
+
+        LookupConfig {
+            selector,
+            value,
+            table,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        layouter.assign_table(
+            || "range table",
+            |mut table| {
+                for row in 0..self.table_rows {
+                    table.assign_cell(
+                        || "entry",
+                        config.table,
+                        row,
+                        || Value::known(Fr::from(row as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for (row, value) in self.values.iter().enumerate() {
+                    region.assign_advice(
+                        || "value",
+                        config.value,
+                        row,
+                        || Value::known(Fr::from(*value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "enable lookup",
+            |mut region| {
+                for row in 0..self.values.len() {
+                    config.selector.enable(&mut region, row)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ProductConfig {
+    selector: Selector,
+    advice: Column<Advice>,
+}
+
+/// Checks `a * b = product` with the gate "product check" over three rows of an advice column
+/// in region "product", like `FactorisationCircuit` but with the product given so that the
+/// gate can fail. A missing product is left unassigned.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct ProductCircuit {
+    pub a: u64,
+    pub b: u64,
+    pub product: Option<u64>,
+}
+
+impl Circuit<Fr> for ProductCircuit {
+    type Config = ProductConfig;
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let selector = meta.selector();
+        let advice = meta.advice_column();
+        meta.create_gate("product check", |meta| {
+            let s = meta.query_selector(selector);
+            let a = meta.query_advice(advice, Rotation::cur());
+            let b = meta.query_advice(advice, Rotation::next());
+            let product = meta.query_advice(advice, Rotation(2));
+            vec![s * (a * b - product)]
+        });
+        ProductConfig { selector, advice }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        layouter.assign_region(
+            || "product",
+            |mut region| {
+                config.selector.enable(&mut region, 0)?;
+                region.assign_advice(
+                    || "a",
+                    config.advice,
+                    0,
+                    || Value::known(Fr::from(self.a)),
+                )?;
+                region.assign_advice(
+                    || "b",
+                    config.advice,
+                    1,
+                    || Value::known(Fr::from(self.b)),
+                )?;
+                if let Some(product) = self.product {
+                    region.assign_advice(
+                        || "product",
+                        config.advice,
+                        2,
+                        || Value::known(Fr::from(product)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Assigns one region per entry, named and sized as given, in the first or the second of two
+/// advice columns. `SimpleFloorPlanner` places each region below the previous ones in the
+/// same column.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct RegionsCircuit {
+    /// Name, advice column and number of rows of each region.
+    pub regions: Vec<(&'static str, usize, usize)>,
+}
+
+impl Circuit<Fr> for RegionsCircuit {
+    type Config = [Column<Advice>; 2];
+
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        [meta.advice_column(), meta.advice_column()]
+    }
+
+    fn synthesize(
+        &self,
+        advice: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        for (name, column, rows) in &self.regions {
+            layouter.assign_region(
+                || *name,
+                |mut region| {
+                    for row in 0..*rows {
+                        region.assign_advice(
+                            || "row",
+                            advice[*column],
+                            row,
+                            || Value::known(Fr::from(row as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Disjoint sets over arbitrary items, used to group cells joined by copy constraints into
+/// equivalence classes.
+#[derive(Clone, Debug)]
+pub(crate) struct UnionFind<T: Clone + Eq + Hash> {
+    ids: HashMap<T, usize>,
+    items: Vec<T>,
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl<T: Clone + Eq + Hash> Default for UnionFind<T> {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            items: vec![],
+            parent: vec![],
+            size: vec![],
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> UnionFind<T> {
+    fn id(&mut self, item: &T) -> usize {
+        if let Some(id) = self.ids.get(item) {
+            return *id;
+        }
+        let id = self.items.len();
+        self.ids.insert(item.clone(), id);
+        self.items.push(item.clone());
+        self.parent.push(id);
+        self.size.push(1);
+        id
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.parent[id] != id {
+            self.parent[id] = self.parent[self.parent[id]];
+            id = self.parent[id];
+        }
+        id
+    }
+
+    pub fn union(&mut self, a: &T, b: &T) {
+        let a = self.id(a);
+        let b = self.id(b);
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+
+    /// Returns the equivalence classes, largest first. Items keep the order in which they
+    /// were first seen.
+    pub fn classes(&mut self) -> Vec<Vec<T>> {
+        let mut class_of_root: HashMap<usize, usize> = HashMap::new();
+        let mut classes: Vec<Vec<T>> = vec![];
+        for id in 0..self.items.len() {
+            let root = self.find(id);
+            let class = *class_of_root.entry(root).or_insert_with(|| {
+                classes.push(vec![]);
+                classes.len() - 1
+            });
+            classes[class].push(self.items[id].clone());
+        }
+        classes.sort_by(|a, b| b.len().cmp(&a.len()));
+        classes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_transitively() {
+        let mut classes = UnionFind::default();
+        classes.union(&"a", &"b");
+        classes.union(&"c", &"d");
+        classes.union(&"b", &"c");
+        classes.union(&"e", &"f");
+        assert_eq!(
+            classes.classes(),
+            vec![vec!["a", "b", "c", "d"], vec!["e", "f"]]
+        );
+    }
+
+    #[test]
+    fn repeated_union_is_a_no_op() {
+        let mut classes = UnionFind::default();
+        classes.union(&1, &2);
+        classes.union(&2, &1);
+        classes.union(&1, &1);
+        assert_eq!(classes.classes(), vec![vec![1, 2]]);
+    }
+
+    #[test]
+    fn largest_class_first() {
+        let mut classes = UnionFind::default();
+        classes.union(&(0, 0), &(0, 1));
+        classes.union(&(1, 0), &(1, 1));
+        classes.union(&(1, 1), &(1, 2));
+        assert_eq!(
+            classes.classes(),
+            vec![vec![(1, 0), (1, 1), (1, 2)], vec![(0, 0), (0, 1)]]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let mut classes = UnionFind::<usize>::default();
+        assert!(classes.classes().is_empty());
+    }
+}
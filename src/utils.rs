@@ -19,6 +19,19 @@ where
     rows.ilog2() + 1
 }
 
+/// Configures a fresh constraint system for the circuit.
+#[cfg_attr(not(feature = "circuit-params"), allow(unused_variables))]
+pub(crate) fn configure<F: RawField, C: Circuit<F>>(
+    #[cfg(feature = "circuit-params")] circuit: &C,
+) -> ConstraintSystem<F> {
+    let mut cs = ConstraintSystem::<F>::default();
+    #[cfg(feature = "circuit-params")]
+    C::configure_with_params(&mut cs, circuit.params());
+    #[cfg(not(feature = "circuit-params"))]
+    C::configure(&mut cs);
+    cs
+}
+
 pub fn derive_circuit_name<ConcreteCircuit>(circuit: &ConcreteCircuit) -> String
 where
     ConcreteCircuit: Debug,
@@ -0,0 +1,294 @@
+use crate::RawField;
+
+/// Largest `n` for which `Auto` recognizes `1/n`.
+const MAX_INVERSE: u64 = 256;
+
+/// How field elements are rendered as text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// Values below 2^64 in hex without prefix, larger values as 0x prefixed hex padded to the
+    /// size of the field.
+    #[default]
+    Compact,
+    /// 0x prefixed hex.
+    Hex,
+    Decimal,
+    /// Decimal, values closer to p than to 0 are shown as `-x` for `p - x`.
+    Signed,
+    /// Number of bits needed to represent the value.
+    BitLength,
+    /// Recognizes small values, negations of small values, powers of two and inverses of
+    /// small integers, and falls back to hex.
+    Auto,
+}
+
+impl ValueFormat {
+    pub fn format<F: RawField>(&self, f: F) -> String {
+        match self {
+            ValueFormat::Compact => {
+                let bytes = to_be_bytes(f);
+                if bit_length(&bytes) <= 64 {
+                    format!("{:x}", to_u64(&bytes))
+                } else {
+                    format!("0x{}", to_hex(&bytes))
+                }
+            }
+            ValueFormat::Hex => format!("0x{}", trim_hex(&to_hex(&to_be_bytes(f)))),
+            ValueFormat::Decimal => to_decimal(&to_be_bytes(f)),
+            ValueFormat::Signed => {
+                let bytes = to_be_bytes(f);
+                let neg_bytes = to_be_bytes(-f);
+                if neg_bytes < bytes {
+                    format!("-{}", to_decimal(&neg_bytes))
+                } else {
+                    to_decimal(&bytes)
+                }
+            }
+            ValueFormat::BitLength => format!("{} bits", bit_length(&to_be_bytes(f))),
+            ValueFormat::Auto => format_auto(f),
+        }
+    }
+
+    /// Reads back a value as it is displayed in this format, `None` if the text is not a value
+    /// or the format does not keep it, like `BitLength`. `0x` prefixed hex, `-x`, `2^n` and
+    /// `1/n` are read in every format.
+    pub(crate) fn parse<F: RawField>(&self, text: &str) -> Option<F> {
+        let text = text.trim();
+        if let Some(text) = text.strip_prefix('-') {
+            return self.parse::<F>(text).map(|f| -f);
+        }
+        if let Some(exponent) = text.strip_prefix("2^") {
+            let exponent: usize = exponent.parse().ok()?;
+            if exponent >= F::from(0u64).to_repr().as_ref().len() * 8 {
+                return None;
+            }
+            return Some((0..exponent).fold(F::from(1u64), |acc, _| acc + acc));
+        }
+        if let Some(n) = text.strip_prefix("1/") {
+            return Option::from(parse_digits::<F>(n, 10)?.invert());
+        }
+        if let Some(hex) = text.strip_prefix("0x") {
+            return parse_digits(hex, 16);
+        }
+        match self {
+            ValueFormat::Compact => parse_digits(text, 16),
+            ValueFormat::BitLength => None,
+            _ => parse_digits(text, 10),
+        }
+    }
+}
+
+/// Reads the digits as a number modulo p.
+fn parse_digits<F: RawField>(digits: &str, radix: u32) -> Option<F> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(F::from(0u64), |acc, c| {
+        let digit = c.to_digit(radix)?;
+        Some(acc * F::from(radix as u64) + F::from(digit as u64))
+    })
+}
+
+fn format_auto<F: RawField>(f: F) -> String {
+    let bytes = to_be_bytes(f);
+    if bit_length(&bytes) <= 64 {
+        return to_u64(&bytes).to_string();
+    }
+
+    let neg_bytes = to_be_bytes(-f);
+    if bit_length(&neg_bytes) <= 64 {
+        return format!("-{}", to_u64(&neg_bytes));
+    }
+
+    let bits = bit_length(&bytes);
+    if bytes.iter().map(|b| b.count_ones()).sum::<u32>() == 1 {
+        return format!("2^{}", bits - 1);
+    }
+
+    let one = F::from(1u64);
+    for n in 2..=MAX_INVERSE {
+        if f * F::from(n) == one {
+            return format!("1/{}", n);
+        }
+    }
+
+    format!("0x{}", trim_hex(&to_hex(&bytes)))
+}
+
+/// Returns the canonical representation of the value in big endian. `PrimeField` leaves the
+/// endianness of `to_repr` to the field, so it is found out from the repr of one.
+fn to_be_bytes<F: RawField>(f: F) -> Vec<u8> {
+    let mut bytes = f.to_repr().as_ref().to_vec();
+    let one = F::from(1u64).to_repr();
+    if one.as_ref().first() == Some(&1) {
+        bytes.reverse();
+    }
+    bytes
+}
+
+fn bit_length(be_bytes: &[u8]) -> usize {
+    match be_bytes.iter().position(|b| *b != 0) {
+        Some(i) => (be_bytes.len() - i - 1) * 8 + (8 - be_bytes[i].leading_zeros() as usize),
+        None => 0,
+    }
+}
+
+/// Low 64 bits of the value.
+fn to_u64(be_bytes: &[u8]) -> u64 {
+    be_bytes
+        .iter()
+        .rev()
+        .take(8)
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn to_hex(be_bytes: &[u8]) -> String {
+    be_bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn trim_hex(hex: &str) -> &str {
+    let trimmed = hex.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0"
+    } else {
+        trimmed
+    }
+}
+
+fn to_decimal(be_bytes: &[u8]) -> String {
+    let mut number = be_bytes.to_vec();
+    let mut digits = vec![];
+    while number.iter().any(|b| *b != 0) {
+        // long division by 10
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+    if digits.is_empty() {
+        "0".to_string()
+    } else {
+        digits.iter().rev().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::halo2_proofs::halo2curves::{bn256::Fr, pasta::Fp};
+
+    use super::*;
+
+    /// Formats that do not depend on the modulus.
+    fn check_small_values<F: RawField>() {
+        assert_eq!(ValueFormat::Compact.format(F::from(255)), "ff");
+        assert_eq!(ValueFormat::Hex.format(F::from(0)), "0x0");
+        assert_eq!(ValueFormat::Hex.format(F::from(256)), "0x100");
+        assert_eq!(ValueFormat::Decimal.format(F::from(1234)), "1234");
+        assert_eq!(ValueFormat::Signed.format(F::from(5)), "5");
+        assert_eq!(ValueFormat::Signed.format(-F::from(5)), "-5");
+        assert_eq!(ValueFormat::BitLength.format(F::from(255)), "8 bits");
+        assert_eq!(ValueFormat::BitLength.format(F::from(0)), "0 bits");
+
+        assert_eq!(ValueFormat::Auto.format(F::from(7)), "7");
+        assert_eq!(ValueFormat::Auto.format(-F::from(3)), "-3");
+        let two_pow_100 = (0..100).fold(F::from(1), |acc, _| acc + acc);
+        assert_eq!(ValueFormat::Auto.format(two_pow_100), "2^100");
+        assert_eq!(
+            ValueFormat::Auto.format(F::from(3).invert().unwrap()),
+            "1/3"
+        );
+        assert_eq!(
+            ValueFormat::Auto.format(F::from(MAX_INVERSE).invert().unwrap()),
+            format!("1/{}", MAX_INVERSE)
+        );
+    }
+
+    #[test]
+    fn formats_bn256_values() {
+        check_small_values::<Fr>();
+        assert_eq!(
+            ValueFormat::Decimal.format(-Fr::from(1)),
+            "21888242871839275222246405745257275088548364400416034343698204186575808495616"
+        );
+        assert_eq!(
+            ValueFormat::Hex.format(-Fr::from(1)),
+            "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000"
+        );
+        assert_eq!(
+            ValueFormat::Compact.format(Fr::from(u64::MAX) + Fr::from(1)),
+            "0x0000000000000000000000000000000000000000000000010000000000000000"
+        );
+    }
+
+    #[test]
+    fn formats_pasta_values() {
+        check_small_values::<Fp>();
+        assert_eq!(
+            ValueFormat::Decimal.format(-Fp::from(1)),
+            "28948022309329048855892746252171976963363056481941560715954676764349967630336"
+        );
+        assert_eq!(
+            ValueFormat::Hex.format(-Fp::from(1)),
+            "0x40000000000000000000000000000000224698fc094cf91b992d30ed00000000"
+        );
+    }
+
+    #[test]
+    fn parses_formatted_values() {
+        let values = [
+            Fr::from(0),
+            Fr::from(255),
+            Fr::from(u64::MAX) + Fr::from(1),
+            -Fr::from(5),
+            Fr::from(3).invert().unwrap(),
+        ];
+        for format in [
+            ValueFormat::Compact,
+            ValueFormat::Hex,
+            ValueFormat::Decimal,
+            ValueFormat::Signed,
+            ValueFormat::Auto,
+        ] {
+            for value in values {
+                assert_eq!(format.parse::<Fr>(&format.format(value)), Some(value));
+            }
+        }
+        assert_eq!(ValueFormat::BitLength.parse::<Fr>("8 bits"), None);
+        assert_eq!(ValueFormat::Decimal.parse::<Fr>("12a"), None);
+        assert_eq!(ValueFormat::Decimal.parse::<Fr>(""), None);
+    }
+
+    #[test]
+    fn reads_repr_as_big_endian() {
+        let bytes = to_be_bytes(Fr::from(0x0102));
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(bytes[30..], [1, 2]);
+        let bytes = to_be_bytes(Fp::from(0x0102));
+        assert_eq!(bytes[30..], [1, 2]);
+    }
+
+    #[test]
+    fn converts_any_number_of_bytes() {
+        assert_eq!(to_decimal(&[]), "0");
+        assert_eq!(to_decimal(&[0, 0, 0]), "0");
+        assert_eq!(to_decimal(&[1, 0]), "256");
+        assert_eq!(to_decimal(&[0xff; 8]), u64::MAX.to_string());
+        // 2^384, one more than the largest 48 byte value
+        let mut bytes = vec![1];
+        bytes.extend([0; 48]);
+        assert_eq!(
+            to_decimal(&bytes),
+            "39402006196394479212279040100143613805079739270465446667948293404245721771497210611414266254884915640806627990306816"
+        );
+        assert_eq!(bit_length(&bytes), 385);
+        assert_eq!(bit_length(&[0, 0, 0x10]), 5);
+        assert_eq!(to_u64(&bytes), 0);
+        assert_eq!(to_u64(&[0x12, 0x34]), 0x1234);
+        assert_eq!(trim_hex("000"), "0");
+        assert_eq!(trim_hex("00a0"), "a0");
+    }
+}